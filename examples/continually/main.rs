@@ -16,8 +16,9 @@ struct Args {
     #[arg(long, group = "input")]
     load: Option<PathBuf>,
 
+    /// One of: creative, balanced, precise.
     #[arg(long)]
-    style: String,
+    style: ConversationStyle,
 }
 
 #[tokio::main]
@@ -28,13 +29,7 @@ async fn main() {
             .map(std::io::BufReader::new)
             .unwrap();
         let cookies: Vec<CookieInFile> = serde_json::from_reader(file).unwrap();
-        let style = match args.style.as_str() {
-            "creative" => ConversationStyle::Creative,
-            "balanced" => ConversationStyle::Balanced,
-            "precise" => ConversationStyle::Precise,
-            _ => panic!("style must be one of: creative, balanced, precise"),
-        };
-        let mut bot = ChatSession::create(style, &cookies).await.unwrap();
+        let mut bot = ChatSession::create(args.style, &cookies).await.unwrap();
         println!("Ask the question please:");
         let question = stdio::read_line();
         println!("Waiting for bing for response ...");
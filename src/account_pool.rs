@@ -0,0 +1,223 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    ChatSession, ClientSettings, ConversationMetaCreatingError, ConversationStyle, CookieInFile,
+    CookieStore,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// A bing account.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Account {
+    /// Unique name of the account, recorded in the [`ChatSession`]s created with it.
+    pub name: String,
+    /// Cookies of the account when it joined the pool,
+    /// the ones bing rotated since are in [`AccountPool::cookie_store`].
+    pub cookies: Vec<CookieInFile>,
+}
+
+/// How an [`AccountPool`] picks the account for the next conversation.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub enum Strategy {
+    /// Use the accounts in turn.
+    #[default]
+    RoundRobin,
+    /// Use the account which created the fewest conversations.
+    LeastUsed,
+}
+
+/// Health of an account in an [`AccountPool`].
+#[derive(Debug, Clone)]
+pub struct AccountHealth {
+    /// Name of the account.
+    pub name: String,
+    /// How many conversations were created with the account, including the ones being created.
+    pub uses: usize,
+    /// How many conversation creatings failed in a row.
+    pub failures: usize,
+    /// Until when the account is not used, `None` if it is healthy.
+    pub unhealthy_until: Option<Instant>,
+}
+
+impl AccountHealth {
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.unhealthy_until
+            .map(|until| until <= now)
+            .unwrap_or(true)
+    }
+}
+
+#[derive(Debug)]
+struct PoolState {
+    health: Vec<AccountHealth>,
+    next: usize,
+}
+
+/// Several bing accounts to create conversations with,
+/// an account is cooled down for a while after bing rejects it.
+#[derive(Debug)]
+pub struct AccountPool {
+    accounts: Vec<Account>,
+    stores: Vec<CookieStore>,
+    strategy: Strategy,
+    cooldown: Duration,
+    state: Mutex<PoolState>,
+}
+
+impl AccountPool {
+    /// Create an [`AccountPool`] of the accounts.
+    pub fn new(accounts: Vec<Account>, strategy: Strategy) -> Self {
+        let health = accounts
+            .iter()
+            .map(|account| AccountHealth {
+                name: account.name.clone(),
+                uses: 0,
+                failures: 0,
+                unhealthy_until: None,
+            })
+            .collect();
+        let stores = accounts
+            .iter()
+            .map(|account| CookieStore::new(&account.cookies))
+            .collect();
+        Self {
+            accounts,
+            stores,
+            strategy,
+            cooldown: Duration::from_secs(600),
+            state: Mutex::new(PoolState { health, next: 0 }),
+        }
+    }
+
+    /// Set how long an unhealthy account is not used, doubled for each failure in a row.
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Create the [`CookieStore`] of each account with `store`,
+    /// eg. to save the cookies bing rotates with [`CookieStore::with_on_update`].
+    pub fn with_cookie_stores(mut self, store: impl Fn(&Account) -> CookieStore) -> Self {
+        self.stores = self.accounts.iter().map(store).collect();
+        self
+    }
+
+    /// Get an account by its name, eg. the owner of a dumped [`ChatSession`].
+    pub fn account(&self, name: &str) -> Option<&Account> {
+        self.accounts.iter().find(|account| account.name == name)
+    }
+
+    /// The current cookies of an account, shared by the sessions created with it.
+    pub fn cookie_store(&self, name: &str) -> Option<&CookieStore> {
+        self.accounts
+            .iter()
+            .position(|account| account.name == name)
+            .map(|index| &self.stores[index])
+    }
+
+    /// Health of all accounts.
+    pub fn health(&self) -> Vec<AccountHealth> {
+        self.state.lock().unwrap().health.clone()
+    }
+
+    /// Create a [`ChatSession`] with the next healthy account,
+    /// trying other accounts if bing rejects it.
+    pub async fn create_session(
+        &self,
+        style: ConversationStyle,
+        settings: ClientSettings,
+    ) -> Result<ChatSession> {
+        let mut tried = vec![];
+        let mut last_error = None;
+        while let Some(index) = self.reserve(&tried) {
+            if !tried.is_empty() {
+                if let Some(metrics) = &settings.metrics {
+                    metrics.record_retry();
+                }
+            }
+            tried.push(index);
+            let account = &self.accounts[index];
+            let session = ChatSession::create_in_store(
+                style,
+                &self.stores[index],
+                settings.clone(),
+                Some(account.name.clone()),
+            )
+            .await;
+            match session {
+                Ok(session) => {
+                    self.mark_healthy(index);
+                    return Ok(session);
+                }
+                Err(
+                    e @ (ConversationMetaCreatingError::Unauthorized
+                    | ConversationMetaCreatingError::Throttled),
+                ) => {
+                    self.release(index);
+                    self.mark_unhealthy(&account.name);
+                    last_error = Some(e);
+                }
+                Err(e) => {
+                    self.release(index);
+                    return Err(e.into());
+                }
+            }
+        }
+        Err(AccountPoolError::NoHealthyAccount(last_error))
+    }
+
+    /// Stop using an account for a while, eg. after it is throttled while chatting.
+    pub fn mark_unhealthy(&self, name: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(health) = state.health.iter_mut().find(|it| it.name == name) {
+            health.failures += 1;
+            let factor = 1u32 << (health.failures - 1).min(6);
+            health.unhealthy_until = Some(Instant::now() + self.cooldown * factor);
+        }
+    }
+
+    fn mark_healthy(&self, index: usize) {
+        let mut state = self.state.lock().unwrap();
+        let health = &mut state.health[index];
+        health.failures = 0;
+        health.unhealthy_until = None;
+    }
+
+    /// Pick the next healthy account not `tried` yet and count a use of it,
+    /// before creating the conversation so concurrent callers spread over the accounts.
+    fn reserve(&self, tried: &[usize]) -> Option<usize> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let count = self.accounts.len();
+        let mut candidates = (0..count)
+            .map(|offset| (state.next + offset) % count)
+            .filter(|index| !tried.contains(index) && state.health[*index].is_healthy(now));
+        let index = match self.strategy {
+            Strategy::RoundRobin => candidates.next()?,
+            Strategy::LeastUsed => candidates.min_by_key(|&index| state.health[index].uses)?,
+        };
+        state.next = (index + 1) % count;
+        state.health[index].uses += 1;
+        Some(index)
+    }
+
+    /// Take back the use counted by [`reserve`](AccountPool::reserve) after a failure.
+    fn release(&self, index: usize) {
+        self.state.lock().unwrap().health[index].uses -= 1;
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum AccountPoolError {
+    /// the error of the last account tried, `None` if all were cooling down.
+    #[error("No healthy account in the pool")]
+    NoHealthyAccount(#[source] Option<ConversationMetaCreatingError>),
+    #[error("Failed to create conversation")]
+    Create(#[from] ConversationMetaCreatingError),
+}
+
+pub type Result<T> = std::result::Result<T, AccountPoolError>;
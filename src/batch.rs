@@ -0,0 +1,326 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs::File,
+    io::{self, LineWriter, Write},
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    ChatSession, ClientSettings, ConversationStyle, CookieInFile, NewBingResponseMessage,
+    TurnResult,
+};
+
+/// The answer to one prompt of a batch, a line of the checkpoint file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchItem {
+    /// position of the prompt in the batch.
+    pub index: usize,
+    /// the prompt sent.
+    pub prompt: String,
+    /// the answer, `None` if sending the prompt failed.
+    pub answer: Option<NewBingResponseMessage>,
+    /// why sending the prompt failed.
+    pub error: Option<String>,
+    /// from sending the prompt to the end of the answer, including creating a conversation.
+    pub latency: Duration,
+    /// from connecting to the chat hub to the first text of the answer.
+    pub time_to_first_token: Option<Duration>,
+}
+
+impl BatchItem {
+    fn new(
+        index: usize,
+        prompt: String,
+        result: std::result::Result<TurnResult, String>,
+        latency: Duration,
+    ) -> Self {
+        let (answer, error, time_to_first_token) = match result {
+            Ok(result) => (Some(result.message), None, result.time_to_first_token),
+            Err(e) => (None, Some(e), None),
+        };
+        Self {
+            index,
+            prompt,
+            answer,
+            error,
+            latency,
+            time_to_first_token,
+        }
+    }
+}
+
+/// Sends many prompts, several at a time, eg. for evaluating the answers.
+///
+/// Each worker creates a [`ChatSession`] and reuses it for its prompts,
+/// starting a new conversation every [`with_prompts_per_conversation`](BatchRunner::with_prompts_per_conversation)
+/// prompts and after a failure.
+#[derive(Debug, Clone)]
+pub struct BatchRunner {
+    style: ConversationStyle,
+    cookies: Vec<CookieInFile>,
+    settings: ClientSettings,
+    concurrency: usize,
+    prompts_per_conversation: usize,
+}
+
+impl BatchRunner {
+    /// Create a [`BatchRunner`] creating conversations with the cookies and settings,
+    /// sending 4 prompts at a time, each in a new conversation.
+    pub fn new(
+        style: ConversationStyle,
+        cookies: &[CookieInFile],
+        settings: ClientSettings,
+    ) -> Self {
+        Self {
+            style,
+            cookies: cookies.to_vec(),
+            settings,
+            concurrency: 4,
+            prompts_per_conversation: 1,
+        }
+    }
+
+    /// Send at most `concurrency` prompts at a time.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Send up to `prompts` prompts in the same conversation, so later ones see the earlier answers.
+    pub fn with_prompts_per_conversation(mut self, prompts: usize) -> Self {
+        self.prompts_per_conversation = prompts.max(1);
+        self
+    }
+
+    /// Send the prompts, return the answers in the order of the prompts.
+    pub async fn run(&self, prompts: &[impl AsRef<str>]) -> Vec<BatchItem> {
+        let pending = prompts
+            .iter()
+            .map(|it| it.as_ref().to_string())
+            .enumerate()
+            .collect();
+        let mut items = self.send_all(pending, &|_| true).await;
+        items.sort_by_key(|it| it.index);
+        items
+    }
+
+    /// Send the prompts not answered in the checkpoint file yet, appending each answer
+    /// to it as a line of json once received, see [`BatchItem`].
+    /// Failed prompts are sent again, as are prompts changed since the answer was written,
+    /// and the file can be resumed after an interruption.
+    /// Return the answers to all the prompts, including the ones from earlier runs.
+    ///
+    /// No prompt is sent after failing to write an answer, the answers being received are dropped
+    /// and the error is returned, the checkpoint holds the answers written before.
+    pub async fn run_with_checkpoint(
+        &self,
+        prompts: &[impl AsRef<str>],
+        path: impl AsRef<Path>,
+    ) -> Result<Vec<BatchItem>> {
+        let path = path.as_ref();
+        let checkpoint = if path.exists() {
+            std::fs::read_to_string(path)?
+        } else {
+            String::new()
+        };
+        let (read, parsed) = read_checkpoint(&checkpoint)?;
+        let mut items = BTreeMap::new();
+        // the last line of a prompt sent several times wins
+        for item in read {
+            if prompts
+                .get(item.index)
+                .is_some_and(|prompt| prompt.as_ref() == item.prompt)
+            {
+                items.insert(item.index, item);
+            }
+        }
+        let pending: VecDeque<_> = prompts
+            .iter()
+            .map(|it| it.as_ref().to_string())
+            .enumerate()
+            .filter(|(index, _)| !matches!(items.get(index), Some(item) if item.answer.is_some()))
+            .collect();
+        for _ in pending
+            .iter()
+            .filter(|(index, _)| items.contains_key(index))
+        {
+            self.record_retry();
+        }
+        let mut file = File::options().create(true).append(true).open(path)?;
+        if parsed < checkpoint.len() {
+            // drop the line cut short
+            file.set_len(parsed as u64)?;
+        }
+        if !checkpoint[..parsed].is_empty() && !checkpoint[..parsed].ends_with('\n') {
+            writeln!(file)?;
+        }
+        let file = Mutex::new(LineWriter::new(file));
+        let failed_write = Mutex::new(None);
+        let write = |item: &BatchItem| {
+            let result = serde_json::to_string(item)
+                .map_err(io::Error::from)
+                .and_then(|line| writeln!(file.lock().unwrap(), "{line}"));
+            let written = result.is_ok();
+            if let Err(e) = result {
+                failed_write.lock().unwrap().get_or_insert(e);
+            }
+            written
+        };
+        let sent = self.send_all(pending, &write).await;
+        if let Some(e) = failed_write.into_inner().unwrap() {
+            return Err(e.into());
+        }
+        items.extend(sent.into_iter().map(|it| (it.index, it)));
+        Ok(items.into_values().collect())
+    }
+
+    /// Send the prompts with the workers, `on_item` returns false to stop sending
+    /// the prompts left in the queue.
+    async fn send_all(
+        &self,
+        pending: VecDeque<(usize, String)>,
+        on_item: &(dyn Fn(&BatchItem) -> bool + Sync),
+    ) -> Vec<BatchItem> {
+        let workers = self.concurrency.min(pending.len());
+        let queue = Mutex::new(pending);
+        join_all((0..workers).map(|_| self.worker(&queue, on_item)))
+            .await
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// Send prompts from the queue one after another, until it is empty.
+    async fn worker(
+        &self,
+        queue: &Mutex<VecDeque<(usize, String)>>,
+        on_item: &(dyn Fn(&BatchItem) -> bool + Sync),
+    ) -> Vec<BatchItem> {
+        let mut session = None;
+        let mut sent = 0;
+        let mut failed = false;
+        let mut items = vec![];
+        loop {
+            let Some((index, prompt)) = queue.lock().unwrap().pop_front() else {
+                break;
+            };
+            if failed {
+                self.record_retry();
+            }
+            let start = Instant::now();
+            let result = self.send(&mut session, &mut sent, &prompt).await;
+            failed = result.is_err();
+            if failed {
+                // eg. an ended conversation, continue in a new one
+                sent = self.prompts_per_conversation;
+            }
+            let item = BatchItem::new(index, prompt, result, start.elapsed());
+            if !on_item(&item) {
+                // the other workers stop after their current prompt
+                queue.lock().unwrap().clear();
+            }
+            items.push(item);
+        }
+        items
+    }
+
+    async fn send(
+        &self,
+        session: &mut Option<ChatSession>,
+        sent: &mut usize,
+        prompt: &str,
+    ) -> std::result::Result<TurnResult, String> {
+        let session = match session {
+            Some(session) if *sent >= self.prompts_per_conversation => {
+                session.reset().await.map_err(|e| e.to_string())?;
+                *sent = 0;
+                session
+            }
+            Some(session) => session,
+            None => {
+                *sent = 0;
+                session.insert(
+                    ChatSession::create_with_settings(
+                        self.style,
+                        &self.cookies,
+                        self.settings.clone(),
+                    )
+                    .await
+                    .map_err(|e| e.to_string())?,
+                )
+            }
+        };
+        *sent += 1;
+        session
+            .send_message_full(prompt)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    fn record_retry(&self) {
+        if let Some(metrics) = &self.settings.metrics {
+            metrics.record_retry();
+        }
+    }
+}
+
+/// The items of the checkpoint and the length of the lines parsed,
+/// the last line is skipped if it was cut short by an interruption.
+fn read_checkpoint(checkpoint: &str) -> Result<(Vec<BatchItem>, usize)> {
+    let mut items = vec![];
+    let mut parsed = 0;
+    let mut lines = checkpoint.split_inclusive('\n').peekable();
+    while let Some(line) = lines.next() {
+        if !line.trim().is_empty() {
+            match serde_json::from_str(line) {
+                Ok(item) => items.push(item),
+                Err(_) if lines.peek().is_none() => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        parsed += line.len();
+    }
+    Ok((items, parsed))
+}
+
+#[derive(Error, Debug)]
+pub enum BatchError {
+    #[error("Failed to read or write the checkpoint file")]
+    Io(#[from] io::Error),
+    #[error("Failed to parse the checkpoint file")]
+    Parse(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, BatchError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Endpoints;
+
+    #[tokio::test]
+    async fn stop_sending_when_asked() {
+        // nothing listens there, each prompt fails right away
+        let settings = ClientSettings {
+            endpoints: Endpoints {
+                create_conversation: "http://127.0.0.1:1/create".to_string(),
+                ..Endpoints::default()
+            },
+            ..ClientSettings::default()
+        };
+        let runner =
+            BatchRunner::new(ConversationStyle::Balanced, &[], settings).with_concurrency(1);
+        let pending = (0..5).map(|index| (index, format!("{index}"))).collect();
+        let items = runner.send_all(pending, &|item| item.index < 2).await;
+        // the item stopping them is kept
+        let sent: Vec<_> = items.iter().map(|it| it.index).collect();
+        assert_eq!(sent, [0, 1, 2]);
+        assert!(items.iter().all(|it| it.error.is_some()));
+    }
+}
@@ -0,0 +1,54 @@
+use std::{fs::File, io::BufReader, net::SocketAddr, path::PathBuf, process::ExitCode, sync::Arc};
+
+use clap::Parser;
+use edge_gpt::{ClientSettings, CookieInFile, OpenAiServer};
+
+/// Serve bing chat with the OpenAI Chat Completions API
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Cookie file of the bing account, empty for chatting anonymously.
+    #[arg(long, env = "EDGE_GPT_COOKIES")]
+    cookies: Option<PathBuf>,
+
+    /// Address to listen on.
+    #[arg(long, env = "EDGE_GPT_LISTEN", default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+    let cookies: Vec<CookieInFile> = match &args.cookies {
+        Some(path) => match File::open(path)
+            .map(BufReader::new)
+            .map_err(|e| e.to_string())
+            .and_then(|file| serde_json::from_reader(file).map_err(|e| e.to_string()))
+        {
+            Ok(cookies) => cookies,
+            Err(e) => {
+                eprintln!("Failed to load cookies from {}: {e}", path.display());
+                return ExitCode::FAILURE;
+            }
+        },
+        None => vec![],
+    };
+    let server = Arc::new(OpenAiServer::new(cookies, ClientSettings::default()));
+    let result = axum::Server::try_bind(&args.listen).map(|builder| {
+        eprintln!("Listening on http://{}", args.listen);
+        builder.serve(server.router().into_make_service())
+    });
+    match result {
+        Ok(serving) => match serving.await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("Server error: {e}");
+                ExitCode::FAILURE
+            }
+        },
+        Err(e) => {
+            eprintln!("Failed to listen on {}: {e}", args.listen);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use std::{
+    env,
+    fs::File,
+    io::{self, BufReader, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::Parser;
+use edge_gpt::{
+    ChatSession, ChatStream, ClientSettings, ConversationStyle, CookieInFile,
+    NewBingResponseMessage, RenderFormat, StreamExt, TextDelta,
+};
+use serde::Deserialize;
+use tokio::io::{stdin, AsyncBufReadExt};
+
+/// Chat with new Bing in the terminal
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Cookie file of the bing account, chat anonymously if not given.
+    #[arg(long, env = "EDGE_GPT_COOKIES")]
+    cookies: Option<PathBuf>,
+
+    /// HTTP proxy for the requests and the chat hub, eg. http://127.0.0.1:7890.
+    #[arg(long, env = "EDGE_GPT_PROXY")]
+    proxy: Option<String>,
+
+    /// Conversation style: creative, balanced or precise.
+    #[arg(long, env = "EDGE_GPT_STYLE")]
+    style: Option<ConversationStyle>,
+
+    /// Config file, defaults to ~/.config/edge-gpt/config.json.
+    #[arg(long, env = "EDGE_GPT_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Continue a session saved with `/save`.
+    #[arg(long)]
+    load: Option<PathBuf>,
+}
+
+/// Fields of the config file, overridden by flags and environment variables.
+#[derive(Deserialize, Debug, Default)]
+struct Config {
+    cookies: Option<PathBuf>,
+    proxy: Option<String>,
+    style: Option<String>,
+}
+
+/// Exit codes, following sysexits.h.
+const EXIT_NO_INPUT: u8 = 66;
+const EXIT_UNAVAILABLE: u8 = 69;
+const EXIT_IO_ERROR: u8 = 74;
+const EXIT_CONFIG: u8 = 78;
+
+const HELP: &str = "\
+Commands:
+  /new             start a new conversation
+  /style STYLE     change the style to creative, balanced or precise
+  /save PATH       save the session, continue it later with `--load PATH`
+  /load PATH       continue a saved session
+  /export PATH     write the transcript as markdown
+  /suggest N       send the N-th suggested response
+  /help            show this help
+  /quit            exit";
+
+struct Repl {
+    cookies: Vec<CookieInFile>,
+    proxy: Option<String>,
+    style: ConversationStyle,
+    session: ChatSession,
+    /// (question, answer) of each turn in this run.
+    history: Vec<(String, NewBingResponseMessage)>,
+}
+
+impl Repl {
+    async fn ask(&mut self, question: &str) -> Result<(), String> {
+        let stream = self
+            .session
+            .chat_stream(question)
+            .await
+            .map_err(|e| e.to_string())?;
+        self.print_answer(question, stream).await
+    }
+
+    async fn print_answer(&mut self, question: &str, mut stream: ChatStream) -> Result<(), String> {
+        let mut printed = String::new();
+        let mut last = None;
+        while let Some(response) = stream.next().await {
+            let response = response.map_err(|e| e.to_string())?;
+            let text = response.text.trim();
+            match TextDelta::between(&printed, text) {
+                TextDelta::Append(delta) => print!("{delta}"),
+                // the printed text cannot be erased, print the new answer on a line of its own
+                TextDelta::Rewrite(text) => print!("\n{text}"),
+            }
+            io::stdout().flush().ok();
+            printed = text.to_string();
+            last = Some(response);
+        }
+        println!();
+        let response = last.ok_or("No response received")?;
+        for (i, source_attribution) in response.source_attributions.iter().enumerate() {
+            println!("[{}]: {}", i + 1, source_attribution);
+        }
+        for (i, suggested_response) in response.suggestions.iter().enumerate() {
+            println!("/suggest {}: {}", i + 1, suggested_response);
+        }
+        self.history.push((question.to_string(), response));
+        Ok(())
+    }
+
+    async fn command(&mut self, line: &str) -> Result<bool, String> {
+        let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let argument = argument.trim();
+        match command {
+            "/new" => {
+                self.session.reset().await.map_err(|e| e.to_string())?;
+                self.history.clear();
+                println!("Started a new conversation.");
+            }
+            "/style" => {
+                self.style = argument.parse().map_err(|e| format!("{e}"))?;
+                self.session.set_style(self.style);
+                println!("Style changed to {}.", self.style);
+            }
+            "/save" => {
+                let file = File::create(required_path(argument)?).map_err(|e| e.to_string())?;
+                serde_json::to_writer(file, &self.session).map_err(|e| e.to_string())?;
+                println!("Session saved.");
+            }
+            "/load" => {
+                self.session = load_session(required_path(argument)?)?;
+                self.session.set_cookies(&self.cookies);
+                self.session.settings_mut().proxy = self.proxy.clone();
+                self.style = self.session.style();
+                self.history.clear();
+                println!("Session loaded.");
+            }
+            "/export" => {
+                let mut file = File::create(required_path(argument)?).map_err(|e| e.to_string())?;
+                for (question, answer) in &self.history {
+                    let answer = answer.render(RenderFormat::Markdown);
+                    writeln!(file, "## You\n\n{question}\n\n## Bing\n\n{answer}\n")
+                        .map_err(|e| e.to_string())?;
+                }
+                println!("Transcript exported.");
+            }
+            "/suggest" => {
+                let index: usize = argument
+                    .parse()
+                    .map_err(|_| "Usage: /suggest N".to_string())?;
+                let suggestion = self
+                    .history
+                    .last()
+                    .and_then(|(_, answer)| answer.suggestions.get(index.wrapping_sub(1)))
+                    .cloned()
+                    .ok_or(format!("No suggested response {index}"))?;
+                println!("> {suggestion}");
+                let stream = self
+                    .session
+                    .suggestion_stream(&suggestion)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                self.print_answer(&suggestion.text, stream).await?;
+            }
+            "/help" => println!("{HELP}"),
+            "/quit" | "/exit" => return Ok(false),
+            _ => return Err(format!("Unknown command {command}, see /help")),
+        }
+        Ok(true)
+    }
+}
+
+fn required_path(argument: &str) -> Result<&Path, String> {
+    if argument.is_empty() {
+        Err("A path is required".to_string())
+    } else {
+        Ok(Path::new(argument))
+    }
+}
+
+fn load_session(path: &Path) -> Result<ChatSession, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    serde_json::from_reader(BufReader::new(file)).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+fn load_config(path: Option<&Path>) -> Result<Config, String> {
+    let default_path = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|config| config.join("edge-gpt").join("config.json"));
+    let path = match (path, default_path) {
+        (Some(path), _) => path.to_path_buf(),
+        (None, Some(path)) if path.exists() => path,
+        _ => return Ok(Config::default()),
+    };
+    let file = File::open(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+    serde_json::from_reader(BufReader::new(file)).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+    let config = match load_config(args.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Failed to load config: {e}");
+            return ExitCode::from(EXIT_CONFIG);
+        }
+    };
+    let settings = ClientSettings {
+        proxy: args.proxy.or(config.proxy),
+        ..ClientSettings::default()
+    };
+    let style = match (args.style, config.style.map(|it| it.parse())) {
+        (Some(style), _) => style,
+        (None, Some(Ok(style))) => style,
+        (None, Some(Err(e))) => {
+            eprintln!("Invalid style in config: {e}");
+            return ExitCode::from(EXIT_CONFIG);
+        }
+        (None, None) => ConversationStyle::Balanced,
+    };
+    let cookies = match args.cookies.or(config.cookies) {
+        Some(path) => match File::open(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|file| {
+                serde_json::from_reader(BufReader::new(file)).map_err(|e| e.to_string())
+            }) {
+            Ok(cookies) => cookies,
+            Err(e) => {
+                eprintln!("Failed to load cookies from {}: {e}", path.display());
+                return ExitCode::from(EXIT_NO_INPUT);
+            }
+        },
+        None => vec![],
+    };
+    let session = match args.load {
+        Some(path) => match load_session(&path) {
+            Ok(mut session) => {
+                session.set_cookies(&cookies);
+                session.settings_mut().proxy = settings.proxy.clone();
+                session
+            }
+            Err(e) => {
+                eprintln!("Failed to load session: {e}");
+                return ExitCode::from(EXIT_NO_INPUT);
+            }
+        },
+        None => match ChatSession::create_with_settings(style, &cookies, settings.clone()).await {
+            Ok(session) => session,
+            Err(e) => {
+                eprintln!("Failed to create conversation: {e}");
+                return ExitCode::from(EXIT_UNAVAILABLE);
+            }
+        },
+    };
+    let mut repl = Repl {
+        cookies,
+        proxy: settings.proxy,
+        style: session.style(),
+        session,
+        history: vec![],
+    };
+
+    println!(
+        "Chatting with Bing in {} style, /help for commands.",
+        repl.style
+    );
+    let mut lines = tokio::io::BufReader::new(stdin()).lines();
+    loop {
+        print!("> ");
+        io::stdout().flush().ok();
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("Failed to read input: {e}");
+                return ExitCode::from(EXIT_IO_ERROR);
+            }
+        };
+        let line = line.trim();
+        let result = if line.is_empty() {
+            continue;
+        } else if line.starts_with('/') {
+            match repl.command(line).await {
+                Ok(true) => Ok(()),
+                Ok(false) => break,
+                Err(e) => Err(e),
+            }
+        } else {
+            repl.ask(line).await
+        };
+        if let Err(e) = result {
+            eprintln!("error: {e}");
+        }
+    }
+    ExitCode::SUCCESS
+}
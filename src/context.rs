@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+
+/// A document the user is asking about, eg. the web page they are viewing,
+/// sent along with a message like the Edge sidebar does.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DocumentContext {
+    /// url of the document.
+    pub url: String,
+    /// title of the document.
+    pub title: String,
+    /// text content of the document.
+    pub text: String,
+    /// at most this many characters are sent in one context message.
+    #[serde(default = "default_chunk_chars")]
+    pub chunk_chars: usize,
+    /// at most this many characters of `text` are sent, the rest is dropped.
+    #[serde(default = "default_max_chars")]
+    pub max_chars: usize,
+}
+
+fn default_chunk_chars() -> usize {
+    8000
+}
+
+fn default_max_chars() -> usize {
+    32000
+}
+
+impl DocumentContext {
+    /// Create a [`DocumentContext`] with the default limits.
+    pub fn new(url: &str, title: &str, text: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            title: title.to_string(),
+            text: text.to_string(),
+            chunk_chars: default_chunk_chars(),
+            max_chars: default_max_chars(),
+        }
+    }
+
+    /// Set the size of each chunk and of the whole text sent, in characters.
+    pub fn with_limits(mut self, chunk_chars: usize, max_chars: usize) -> Self {
+        self.chunk_chars = chunk_chars;
+        self.max_chars = max_chars;
+        self
+    }
+
+    /// Split the text, truncated to `max_chars`, into chunks of at most `chunk_chars`,
+    /// breaking at whitespace where possible.
+    pub fn chunks(&self) -> Vec<String> {
+        let text: String = self.text.trim().chars().take(self.max_chars).collect();
+        split_text(&text, self.chunk_chars)
+    }
+}
+
+/// Split the text into chunks of at most `chunk_chars`, breaking at whitespace where possible.
+pub(crate) fn split_text(text: &str, chunk_chars: usize) -> Vec<String> {
+    let chunk_chars = chunk_chars.max(1);
+    let mut rest = text.trim().to_string();
+    let mut chunks = vec![];
+    while !rest.is_empty() {
+        let end = match rest.char_indices().nth(chunk_chars) {
+            None => rest.len(),
+            Some((hard_end, _)) => rest[..hard_end]
+                .rfind(char::is_whitespace)
+                .filter(|&it| it > 0)
+                .unwrap_or(hard_end),
+        };
+        let remaining = rest.split_off(end);
+        chunks.push(rest.trim().to_string());
+        rest = remaining.trim_start().to_string();
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn break_at_whitespace() {
+        assert_eq!(split_text("aaa bbb ccc", 5), ["aaa", "bbb", "ccc"]);
+        assert_eq!(split_text("  aaa\n\nbbb  ", 4), ["aaa", "bbb"]);
+    }
+
+    #[test]
+    fn hard_break_without_whitespace() {
+        assert_eq!(split_text("abcdefgh", 3), ["abc", "def", "gh"]);
+        // zero is one character
+        assert_eq!(split_text("abc", 0), ["a", "b", "c"]);
+        assert!(split_text(" \n ", 3).is_empty());
+    }
+
+    #[test]
+    fn multi_byte_characters() {
+        assert_eq!(
+            split_text("日本語のテキスト", 3),
+            ["日本語", "のテキ", "スト"]
+        );
+        assert_eq!(split_text("héllo wörld", 6), ["héllo", "wörld"]);
+        // the ideographic space is whitespace of 3 bytes
+        assert_eq!(split_text("一二\u{3000}三四", 3), ["一二", "三四"]);
+        for chunk in split_text("🦀🦀🦀 🦀🦀🦀🦀🦀 🦀", 4) {
+            assert!(chunk.chars().count() <= 4, "{chunk}");
+        }
+    }
+
+    #[test]
+    fn truncate_to_max_chars() {
+        let document = DocumentContext::new("https://example.com/", "Example", " ääää bbbb cccc ")
+            .with_limits(5, 7);
+        assert_eq!(document.chunks(), ["ääää", "bb"]);
+
+        let document = DocumentContext::new("https://example.com/", "Example", "ääää bbbb")
+            .with_limits(100, 100);
+        assert_eq!(document.chunks(), ["ääää bbbb"]);
+    }
+}
@@ -0,0 +1,239 @@
+use crate::{
+    util::new_reqwest_client, ChatSession, ClientSettings, ConversationMeta, ConversationStyle,
+    CookieInFile, CookieStore,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error;
+
+/// A conversation in the chat history of an account.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationSummary {
+    /// used for identify a conversation
+    pub conversation_id: String,
+    /// used for identify a conversation
+    pub conversation_signature: String,
+    /// name shown in the chat history
+    #[serde(default)]
+    pub chat_name: Option<String>,
+    /// conversation style the conversation was started with, eg. "Balanced"
+    #[serde(default)]
+    pub tone: Option<String>,
+    /// creation time, in milliseconds since the unix epoch
+    #[serde(default)]
+    pub create_time_utc: Option<u64>,
+    /// last update time, in milliseconds since the unix epoch
+    #[serde(default)]
+    pub update_time_utc: Option<u64>,
+}
+
+/// Conversations of an account, as listed by bing.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationList {
+    /// used for identify a client
+    pub client_id: String,
+    /// conversations in the chat history
+    #[serde(rename = "chats")]
+    pub conversations: Vec<ConversationSummary>,
+}
+
+impl ConversationList {
+    /// Get the [`ConversationMeta`] of a listed conversation.
+    pub fn meta(&self, conversation: &ConversationSummary) -> ConversationMeta {
+        ConversationMeta::new(
+            conversation.conversation_id.clone(),
+            conversation.conversation_signature.clone(),
+            self.client_id.clone(),
+        )
+    }
+}
+
+/// A message in the history of a conversation.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryMessage {
+    /// "user" or "bot"
+    pub author: String,
+    /// text content of the message
+    #[serde(default)]
+    pub text: String,
+    /// `None` for chat messages, eg. "InternalSearchQuery" for others
+    #[serde(default)]
+    pub message_type: Option<String>,
+    /// used for identify a message
+    #[serde(default)]
+    pub message_id: Option<String>,
+    /// creation time, in RFC 3339 format
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+/// Manage the existing conversations of an account.
+#[derive(Debug, Clone)]
+pub struct ConversationManager {
+    client: reqwest::Client,
+    settings: ClientSettings,
+    cookies: CookieStore,
+}
+
+impl ConversationManager {
+    /// Create a [`ConversationManager`] for the account the cookies belong to.
+    pub fn new(cookies: &[CookieInFile]) -> Result<Self> {
+        Self::new_with_settings(cookies, &ClientSettings::default())
+    }
+
+    /// Create a [`ConversationManager`] for the account the cookies belong to,
+    /// sending the requests to the [`Endpoints`](crate::Endpoints) of the settings,
+    /// resumed sessions use the settings too.
+    pub fn new_with_settings(cookies: &[CookieInFile], settings: &ClientSettings) -> Result<Self> {
+        Self::new_with_store(&CookieStore::new(cookies), settings)
+    }
+
+    /// Create a [`ConversationManager`] with the cookies of the store, keeping the cookies
+    /// bing sets in it, resumed sessions [`reset`](ChatSession::reset) with the store too.
+    pub fn new_with_store(store: &CookieStore, settings: &ClientSettings) -> Result<Self> {
+        let client = new_reqwest_client(settings)?
+            .cookie_provider(store.jar())
+            .build()?;
+        Ok(Self {
+            client,
+            settings: settings.clone(),
+            cookies: store.clone(),
+        })
+    }
+
+    /// List the conversations in the chat history.
+    pub async fn list(&self) -> Result<ConversationList> {
+        let request = self
+            .client
+            .get(&self.settings.endpoints.list_conversations)
+            .send();
+        let response = self.cookies.track(request).await?.text().await?;
+        let value: Value = serde_json::from_str(&response)?;
+        check_result(&value)?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Fetch the message history of a conversation.
+    pub async fn messages(&self, conversation: &ConversationMeta) -> Result<Vec<HistoryMessage>> {
+        let trace_id = uuid::Uuid::new_v4().simple().to_string();
+        let request = self
+            .client
+            .get(&self.settings.endpoints.get_conversation)
+            .query(&[
+                ("conversationId", conversation.conversation_id.as_str()),
+                ("source", "cib"),
+                ("participantId", conversation.client_id.as_str()),
+                (
+                    "conversationSignature",
+                    conversation.conversation_signature.as_str(),
+                ),
+                ("traceId", trace_id.as_str()),
+            ])
+            .send();
+        let response = self.cookies.track(request).await?.text().await?;
+        let value: Value = serde_json::from_str(&response)?;
+        check_result(&value)?;
+        match value.get("messages") {
+            Some(messages) => Ok(serde_json::from_value(messages.clone())?),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Delete a conversation from the chat history.
+    pub async fn delete(&self, conversation: &ConversationMeta) -> Result<()> {
+        let body = json!({
+            "conversationId": conversation.conversation_id,
+            "conversationSignature": conversation.conversation_signature,
+            "participant": { "id": conversation.client_id },
+            "source": "cib",
+            "optionsSets": ["autosave"],
+        });
+        self.post(&self.settings.endpoints.delete_conversation, body)
+            .await
+    }
+
+    /// Delete every conversation in the chat history, return how many were deleted.
+    pub async fn delete_all(&self) -> Result<usize> {
+        let list = self.list().await?;
+        for conversation in &list.conversations {
+            self.delete(&list.meta(conversation)).await?;
+        }
+        Ok(list.conversations.len())
+    }
+
+    /// Rename a conversation in the chat history.
+    pub async fn rename(&self, conversation: &ConversationMeta, name: &str) -> Result<()> {
+        let body = json!({
+            "conversationId": conversation.conversation_id,
+            "conversationSignature": conversation.conversation_signature,
+            "participant": { "id": conversation.client_id },
+            "source": "cib",
+            "chatName": name,
+        });
+        self.post(&self.settings.endpoints.rename_conversation, body)
+            .await
+    }
+
+    /// Create a [`ChatSession`] continuing a listed conversation, with the settings and cookies of the manager.
+    pub async fn resume(
+        &self,
+        list: &ConversationList,
+        conversation: &ConversationSummary,
+        style: ConversationStyle,
+    ) -> Result<ChatSession> {
+        let meta = list.meta(conversation);
+        let invocation_id = self
+            .messages(&meta)
+            .await?
+            .iter()
+            .filter(|message| message.author == "user" && message.message_type.is_none())
+            .count();
+        let mut session =
+            ChatSession::from_meta_with_settings(meta, style, invocation_id, self.settings.clone());
+        session.set_cookie_store(self.cookies.clone());
+        Ok(session)
+    }
+
+    async fn post(&self, uri: &str, body: Value) -> Result<()> {
+        let request = self.client.post(uri).json(&body).send();
+        let response = self.cookies.track(request).await?.text().await?;
+        let value: Value = serde_json::from_str(&response)?;
+        check_result(&value)
+    }
+}
+
+fn check_result(value: &Value) -> Result<()> {
+    match value["result"]["value"].as_str() {
+        Some("Success") => Ok(()),
+        None => Err(ConversationManagingError::NoResult),
+        Some(result) => Err(ConversationManagingError::Rejected(
+            value["result"]["message"]
+                .as_str()
+                .unwrap_or(result)
+                .to_string(),
+        )),
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ConversationManagingError {
+    #[error("Failed to send conversation managing request")]
+    Network,
+    #[error("Failed to parse conversation managing result")]
+    ParseRespond(#[from] serde_json::Error),
+    #[error("Conversation managing request rejected: {0}")]
+    Rejected(String),
+    #[error("No result in the conversation managing response")]
+    NoResult,
+}
+
+impl From<reqwest::Error> for ConversationManagingError {
+    fn from(_value: reqwest::Error) -> Self {
+        Self::Network
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ConversationManagingError>;
@@ -1,15 +1,16 @@
-use std::sync::Arc;
-
-use crate::{util::new_reqwest_client, CookieInFile};
+use crate::{
+    trace::CreateTrace, util::new_reqwest_client, Action, ClientSettings, CookieInFile,
+    CookieStore, InvalidProfile, RateLimited, SettingsError,
+};
 use reqwest::{
-    cookie::Jar,
     header::{HeaderMap, HeaderValue},
+    StatusCode,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use thiserror::Error;
 
-fn create_conversation_headers() -> HeaderMap {
+fn create_conversation_headers(settings: &ClientSettings) -> Result<HeaderMap> {
     let mut headers = HeaderMap::new();
     headers.insert(
         "authority",
@@ -18,40 +19,25 @@ fn create_conversation_headers() -> HeaderMap {
     headers.insert("accept", HeaderValue::from_static("text/html,application/xhtml+xml,application/xml;q=0.9,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.7"));
     headers.insert(
         "accept-language",
-        HeaderValue::from_static("en-US,en;q=0.9"),
+        HeaderValue::from_str(&settings.locale.accept_language())
+            .unwrap_or(HeaderValue::from_static("en-US,en;q=0.9")),
     );
     headers.insert("cache-control", HeaderValue::from_static("max-age=0"));
-    headers.insert(
-        "sec-ch-ua",
-        HeaderValue::from_static(
-            "\"Chromium\";v=\"110\", \"Not A(Brand\";v=\"24\", \"Microsoft Edge\";v=\"110\"",
-        ),
-    );
-    headers.insert("sec-ch-ua-arch", HeaderValue::from_static("\"x86\""));
-    headers.insert("sec-ch-ua-bitness", HeaderValue::from_static("\"64\""));
-    headers.insert(
-        "sec-ch-ua-full-version",
-        HeaderValue::from_static("\"110.0.1587.69\""),
-    );
-    headers.insert("sec-ch-ua-full-version-list", HeaderValue::from_static("\"Chromium\";v=\"110.0.5481.192\", \"Not A(Brand\";v=\"24.0.0.0\", \"Microsoft Edge\";v=\"110.0.1587.69\""));
-    headers.insert("sec-ch-ua-mobile", HeaderValue::from_static("?0"));
-    headers.insert("sec-ch-ua-model", HeaderValue::from_static("\"\""));
-    headers.insert(
-        "sec-ch-ua-platform",
-        HeaderValue::from_static("\"Windows\""),
-    );
-    headers.insert(
-        "sec-ch-ua-platform-version",
-        HeaderValue::from_static("\"15.0.0\""),
-    );
+    settings.profile.apply(&mut headers)?;
     headers.insert("sec-fetch-dest", HeaderValue::from_static("document"));
     headers.insert("sec-fetch-mode", HeaderValue::from_static("navigate"));
     headers.insert("sec-fetch-site", HeaderValue::from_static("none"));
     headers.insert("sec-fetch-user", HeaderValue::from_static("?1"));
     headers.insert("upgrade-insecure-requests", HeaderValue::from_static("1"));
     headers.insert("x-edge-shopping-flag", HeaderValue::from_static("1"));
-    headers.insert("x-forwarded-for", HeaderValue::from_static("1.1.1.1"));
-    headers
+    settings.forwarded_for.validate()?;
+    if let Some(forwarded_ip) = settings.forwarded_for.pick() {
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_str(&forwarded_ip.to_string()).unwrap(),
+        );
+    }
+    Ok(headers)
 }
 
 /// Information of a created conversation
@@ -73,32 +59,109 @@ struct ConversationMetaResult {
 }
 
 impl ConversationMeta {
+    /// Build the [`ConversationMeta`] of an existing conversation, eg. one listed by
+    /// [`ConversationManager::list`](crate::ConversationManager::list).
+    pub fn new(conversation_id: String, conversation_signature: String, client_id: String) -> Self {
+        Self {
+            conversation_signature,
+            client_id,
+            conversation_id,
+            result: ConversationMetaResult {
+                value: "Success".to_string(),
+                message: None,
+            },
+        }
+    }
+
     /// Create a conversation with provided cookies, return the [`ConversationMeta`] of the created conversation.
     pub async fn create(cookies: &[CookieInFile]) -> Result<ConversationMeta> {
-        let uri = "https://edgeservices.bing.com/edgesvc/turing/conversation/create"
-            .parse()
-            .unwrap();
-        let cookie_jar: Jar = Jar::default();
-        for CookieInFile { name, value } in cookies {
-            cookie_jar.add_cookie_str(&format!("{name}={value}; Domain=bing.com"), &uri)
-        }
-        let response = new_reqwest_client()
-            .cookie_provider(Arc::new(cookie_jar))
-            .build()?
-            .get(uri)
-            .headers(create_conversation_headers())
-            .send()
-            .await?
-            .text()
-            .await?;
-        let value: Value = serde_json::from_str(&response)?;
-        let result = value["result"]["value"].as_str();
-        match result {
-            Some("Success") => {}
-            _ => return Err(ConversationMetaCreatingError::Network),
+        Self::create_with_settings(cookies, &ClientSettings::default()).await
+    }
+
+    /// Create a conversation with provided cookies and client settings.
+    pub async fn create_with_settings(
+        cookies: &[CookieInFile],
+        settings: &ClientSettings,
+    ) -> Result<ConversationMeta> {
+        Self::create_for_account(cookies, settings, None).await
+    }
+
+    /// Create a conversation with the cookies of the store,
+    /// keeping the cookies bing sets in the response.
+    pub async fn create_with_store(
+        store: &CookieStore,
+        settings: &ClientSettings,
+    ) -> Result<ConversationMeta> {
+        Self::create_in_store(store, settings, None).await
+    }
+
+    /// Create a conversation with the cookies of `account`,
+    /// which is used for the per account rate limits.
+    pub(crate) async fn create_for_account(
+        cookies: &[CookieInFile],
+        settings: &ClientSettings,
+        account: Option<&str>,
+    ) -> Result<ConversationMeta> {
+        Self::create_in_store(&CookieStore::new(cookies), settings, account).await
+    }
+
+    pub(crate) async fn create_in_store(
+        store: &CookieStore,
+        settings: &ClientSettings,
+        account: Option<&str>,
+    ) -> Result<ConversationMeta> {
+        let trace = CreateTrace::new(account);
+        let cookies = store.cookies();
+        let result: Result<ConversationMeta> = async {
+            if let Some(rate_limiter) = &settings.rate_limiter {
+                rate_limiter
+                    .acquire(Action::CreateConversation, account)
+                    .await?;
+            }
+            let uri = &settings.endpoints.create_conversation;
+            let headers = create_conversation_headers(settings)?;
+            let response = new_reqwest_client(settings)?
+                .cookie_provider(store.jar())
+                .build()?
+                .get(uri)
+                .headers(headers)
+                .send()
+                .await?;
+            store.updated(&cookies);
+            match response.status() {
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                    return Err(ConversationMetaCreatingError::Unauthorized)
+                }
+                StatusCode::TOO_MANY_REQUESTS => {
+                    return Err(ConversationMetaCreatingError::Throttled)
+                }
+                _ => {}
+            }
+            let value: Value = serde_json::from_str(&response.text().await?)?;
+            match value["result"]["value"].as_str() {
+                Some("Success") => {}
+                Some("UnauthorizedRequest") | Some("Forbidden") => {
+                    return Err(ConversationMetaCreatingError::Unauthorized)
+                }
+                Some("Throttled") | Some("TooManyRequests") => {
+                    return Err(ConversationMetaCreatingError::Throttled)
+                }
+                result => {
+                    return Err(ConversationMetaCreatingError::Rejected(
+                        value["result"]["message"]
+                            .as_str()
+                            .or(result)
+                            .unwrap_or_default()
+                            .to_string(),
+                    ))
+                }
+            }
+            let meta: ConversationMeta = serde_json::from_value(value)?;
+            Ok(meta)
         }
-        let meta: ConversationMeta = serde_json::from_value(value)?;
-        Ok(meta)
+        .await;
+        trace.finished(&result);
+        result
     }
 }
 
@@ -108,6 +171,22 @@ pub enum ConversationMetaCreatingError {
     Network,
     #[error("Failed to parse conversation meta creating result")]
     ParseRespond(#[from] serde_json::Error),
+    #[error("Unauthorized, the cookies may be invalid or expired")]
+    Unauthorized,
+    #[error("Throttled by bing")]
+    Throttled,
+    #[error("Conversation meta creating rejected: {0}")]
+    Rejected(String),
+    /// The session has no cookies to create a conversation with,
+    /// see [`ChatSession::set_cookies`](crate::ChatSession::set_cookies).
+    #[error("No cookies to create a conversation with")]
+    NoCookies,
+    #[error(transparent)]
+    RateLimited(#[from] RateLimited),
+    #[error(transparent)]
+    InvalidProfile(#[from] InvalidProfile),
+    #[error(transparent)]
+    InvalidSettings(#[from] SettingsError),
 }
 
 impl From<reqwest::Error> for ConversationMetaCreatingError {
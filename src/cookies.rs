@@ -0,0 +1,179 @@
+use std::{fmt, fs::File, future::Future, io, path::Path, sync::Arc, time::Duration};
+
+use reqwest::{
+    cookie::{CookieStore as _, Jar},
+    Url,
+};
+use thiserror::Error;
+
+use crate::{
+    util::{cookie_jar, new_reqwest_client},
+    ClientSettings, CookieInFile,
+};
+
+const BASE_URL: &str = "https://www.bing.com";
+
+type OnUpdate = Arc<dyn Fn(&[CookieInFile]) + Send + Sync>;
+
+/// Cookies of an account, kept up to date with the `Set-Cookie` headers bing sends,
+/// eg. the rotated `_U`, `MUID` and `SRCHHPGUSR`.
+///
+/// Clones share the cookies, create sessions with
+/// [`ChatSession::create_with_store`](crate::ChatSession::create_with_store),
+/// and the other clients with their `new_with_store`, eg.
+/// [`ConversationManager::new_with_store`](crate::ConversationManager::new_with_store).
+/// Write the updated cookies back with [`save`](CookieStore::save) or
+/// [`with_on_update`](CookieStore::with_on_update).
+#[derive(Clone)]
+pub struct CookieStore {
+    jar: Arc<Jar>,
+    base_url: Url,
+    on_update: Option<OnUpdate>,
+}
+
+impl CookieStore {
+    /// Keep the cookies, scoped to `bing.com` and all its subdomains.
+    pub fn new(cookies: &[CookieInFile]) -> Self {
+        Self {
+            jar: Arc::new(cookie_jar(cookies)),
+            base_url: BASE_URL.parse().unwrap(),
+            on_update: None,
+        }
+    }
+
+    /// Scope the cookies to `base_url` instead of `https://www.bing.com`,
+    /// which is also where [`touch`](CookieStore::touch) sends its request.
+    pub fn with_base_url(self, base_url: &str) -> Result<Self> {
+        let base_url: Url = base_url.parse().map_err(|_| CookieError::InvalidUrl)?;
+        let jar = Jar::default();
+        for CookieInFile { name, value } in self.cookies() {
+            jar.add_cookie_str(&format!("{name}={value}"), &base_url);
+        }
+        Ok(Self {
+            jar: Arc::new(jar),
+            base_url,
+            ..self
+        })
+    }
+
+    /// Call `on_update` with all the cookies whenever a response changed them.
+    pub fn with_on_update(
+        mut self,
+        on_update: impl Fn(&[CookieInFile]) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_update = Some(Arc::new(on_update));
+        self
+    }
+
+    /// The current cookies, sorted by name.
+    pub fn cookies(&self) -> Vec<CookieInFile> {
+        let Some(header) = self.jar.cookies(&self.base_url) else {
+            return vec![];
+        };
+        let mut cookies: Vec<_> = header
+            .to_str()
+            .unwrap_or_default()
+            .split("; ")
+            .filter_map(|it| it.split_once('='))
+            .map(|(name, value)| CookieInFile {
+                name: name.to_string(),
+                value: value.to_string(),
+            })
+            .collect();
+        cookies.sort_by(|a, b| a.name.cmp(&b.name));
+        cookies
+    }
+
+    /// Write the current cookies to the file, as a json array of [`CookieInFile`].
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        serde_json::to_writer_pretty(File::create(path)?, &self.cookies())?;
+        Ok(())
+    }
+
+    /// Visit the home page of bing to get fresh cookies before the current ones expire,
+    /// with the profile and proxy of the settings, return whether they changed.
+    pub async fn touch(&self, settings: &ClientSettings) -> Result<bool> {
+        let before = self.cookies();
+        new_reqwest_client(settings)?
+            .cookie_provider(self.jar.clone())
+            .build()?
+            .get(self.base_url.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(self.updated(&before))
+    }
+
+    /// [`touch`](CookieStore::touch) every `interval`, until it fails.
+    ///
+    /// The interval is fixed, the `Expires` and `Max-Age` of the cookies are not looked at,
+    /// so pick one shorter than the lifetime of the cookies.
+    pub async fn refresh_every(&self, interval: Duration, settings: &ClientSettings) -> Result<()> {
+        loop {
+            tokio::time::sleep(interval).await;
+            self.touch(settings).await?;
+        }
+    }
+
+    pub(crate) fn jar(&self) -> Arc<Jar> {
+        self.jar.clone()
+    }
+
+    /// Send a request of a client using the [`jar`](CookieStore::jar),
+    /// notifying [`with_on_update`](CookieStore::with_on_update) if the response changed the cookies.
+    pub(crate) async fn track<T>(&self, request: impl Future<Output = T>) -> T {
+        let before = self.cookies();
+        let response = request.await;
+        self.updated(&before);
+        response
+    }
+
+    /// Notify [`with_on_update`](CookieStore::with_on_update) if the cookies differ from `before`.
+    pub(crate) fn updated(&self, before: &[CookieInFile]) -> bool {
+        let cookies = self.cookies();
+        if cookies == before {
+            return false;
+        }
+        if let Some(on_update) = &self.on_update {
+            on_update(&cookies);
+        }
+        true
+    }
+}
+
+impl Default for CookieStore {
+    fn default() -> Self {
+        Self::new(&[])
+    }
+}
+
+impl fmt::Debug for CookieStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the values are secrets, only show the names
+        let names: Vec<_> = self.cookies().into_iter().map(|it| it.name).collect();
+        f.debug_struct("CookieStore")
+            .field("cookies", &names)
+            .field("base_url", &self.base_url.as_str())
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CookieError {
+    #[error("Failed to send the request refreshing the cookies")]
+    Network,
+    #[error("Invalid base url")]
+    InvalidUrl,
+    #[error("Failed to write the cookies")]
+    Io(#[from] io::Error),
+    #[error("Failed to serialize the cookies")]
+    Serialize(#[from] serde_json::Error),
+}
+
+impl From<reqwest::Error> for CookieError {
+    fn from(_value: reqwest::Error) -> Self {
+        Self::Network
+    }
+}
+
+pub type Result<T> = std::result::Result<T, CookieError>;
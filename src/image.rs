@@ -0,0 +1,205 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::{util::new_reqwest_client, ClientSettings, CookieInFile, CookieStore};
+use reqwest::{header::LOCATION, redirect::Policy, StatusCode};
+use thiserror::Error;
+
+const BASE_URL: &str = "https://www.bing.com";
+
+/// Placeholder images bing returns instead of the ones violating the content policy.
+const BAD_IMAGES: [&str; 6] = [
+    "https://r.bing.com/rp/in-2zU3AJUdkgFe7ZKv19yPBHVs.png",
+    "https://r.bing.com/rp/TX9QuO3WzcCJz1uaaSwQAz39Kb0.jpg",
+    "https://r.bing.com/rp/ZAj8Ixsx9k8jKFC8eafOWaldFjk.jpg",
+    "https://r.bing.com/rp/NakVZZ1bLHxdrUh7M2ZhwRaF-Sc.jpg",
+    "https://r.bing.com/rp/TCZTCoMfQPe9TOCqaQVyf6nUqPs.jpg",
+    "https://r.bing.com/rp/qmKdCPN5H7bmeGS6tSh6ls-1_nY.jpg",
+];
+
+/// Generate images with Bing Image Creator.
+#[derive(Debug, Clone)]
+pub struct ImageGenerator {
+    client: reqwest::Client,
+    cookies: CookieStore,
+    base_url: String,
+    poll_interval: Duration,
+    timeout: Duration,
+}
+
+impl ImageGenerator {
+    /// Create an [`ImageGenerator`] with provided cookies.
+    pub fn new(cookies: &[CookieInFile]) -> Result<Self> {
+        Self::new_with_settings(cookies, &ClientSettings::default())
+    }
+
+    /// Create an [`ImageGenerator`] with provided cookies, sending the requests with the
+    /// profile and through the proxy of the settings, eg. the ones of the chat session.
+    pub fn new_with_settings(cookies: &[CookieInFile], settings: &ClientSettings) -> Result<Self> {
+        Self::new_with_store(&CookieStore::new(cookies), settings)
+    }
+
+    /// Create an [`ImageGenerator`] with the cookies of the store and the settings,
+    /// keeping the cookies bing sets in the store.
+    pub fn new_with_store(store: &CookieStore, settings: &ClientSettings) -> Result<Self> {
+        let client = new_reqwest_client(settings)?
+            .cookie_provider(store.jar())
+            .redirect(Policy::none())
+            .build()?;
+        Ok(Self {
+            client,
+            cookies: store.clone(),
+            base_url: BASE_URL.to_string(),
+            poll_interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(300),
+        })
+    }
+
+    /// Send the requests to `base_url` instead of `https://www.bing.com`.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Set how often to check whether the images are ready, and how long to wait at most.
+    pub fn with_polling(mut self, poll_interval: Duration, timeout: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self.timeout = timeout;
+        self
+    }
+
+    /// Generate images with the prompt, return their urls.
+    pub async fn generate(&self, prompt: &str) -> Result<Vec<String>> {
+        let request_id = self.submit(prompt).await?;
+        self.poll(prompt, &request_id).await
+    }
+
+    /// Submit the prompt, return the id of the image creating request.
+    pub async fn submit(&self, prompt: &str) -> Result<String> {
+        let uri = format!("{}/images/create", self.base_url);
+        // rt=4 is the "fast" mode using boosts, fall back to rt=3 when no boosts left.
+        for rt in ["4", "3"] {
+            let request = self
+                .client
+                .post(&uri)
+                .query(&[("q", prompt), ("rt", rt), ("FORM", "GENCRE")])
+                .form(&[("q", prompt), ("qs", "ds")])
+                .send();
+            let response = self.cookies.track(request).await?;
+            if response.status() == StatusCode::FOUND {
+                let location = response
+                    .headers()
+                    .get(LOCATION)
+                    .and_then(|it| it.to_str().ok())
+                    .unwrap_or("");
+                return request_id_from_location(location).ok_or(ImageGenerationError::NoRedirect);
+            }
+            check_blocked(&response.text().await?.to_lowercase())?;
+        }
+        Err(ImageGenerationError::NoRedirect)
+    }
+
+    /// Wait for the images of an image creating request, return their urls.
+    pub async fn poll(&self, prompt: &str, request_id: &str) -> Result<Vec<String>> {
+        let uri = format!("{}/images/create/async/results/{request_id}", self.base_url);
+        let start = Instant::now();
+        loop {
+            let request = self.client.get(&uri).query(&[("q", prompt)]).send();
+            let response = self.cookies.track(request).await?.text().await?;
+            if !response.trim().is_empty() {
+                if response.contains("\"errorMessage\":\"Pending\"") {
+                    return Err(ImageGenerationError::Blocked);
+                }
+                let urls = parse_image_urls(&response);
+                if urls.is_empty() {
+                    return Err(ImageGenerationError::NoImage);
+                }
+                if urls.iter().any(|url| BAD_IMAGES.contains(&url.as_str())) {
+                    return Err(ImageGenerationError::Blocked);
+                }
+                return Ok(urls);
+            }
+            if start.elapsed() > self.timeout {
+                return Err(ImageGenerationError::Timeout);
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Download the images into `directory`, return the paths of the saved files.
+    pub async fn download(&self, urls: &[String], directory: &Path) -> Result<Vec<PathBuf>> {
+        tokio::fs::create_dir_all(directory).await?;
+        let mut paths = Vec::with_capacity(urls.len());
+        for (i, url) in urls.iter().enumerate() {
+            let response = self.client.get(url).send().await?.error_for_status()?;
+            let content = response.bytes().await?;
+            let path = directory.join(format!("{i}.jpeg"));
+            tokio::fs::write(&path, content).await?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+fn request_id_from_location(location: &str) -> Option<String> {
+    location
+        .split(['?', '&'])
+        .find_map(|it| it.strip_prefix("id="))
+        .map(ToString::to_string)
+}
+
+fn check_blocked(lowercase_page: &str) -> Result<()> {
+    if lowercase_page.contains("this prompt has been blocked") {
+        Err(ImageGenerationError::Blocked)
+    } else if lowercase_page.contains("this prompt is being reviewed") {
+        Err(ImageGenerationError::UnderReview)
+    } else if lowercase_page.contains("we're working hard to offer image creator in more languages")
+    {
+        Err(ImageGenerationError::UnsupportedLanguage)
+    } else {
+        Ok(())
+    }
+}
+
+/// Find the `src` of each `<img>` in the result page, without the size parameters.
+fn parse_image_urls(page: &str) -> Vec<String> {
+    let mut urls: Vec<String> = page
+        .split("src=\"")
+        .skip(1)
+        .filter_map(|it| it.split('"').next())
+        .filter(|it| it.starts_with("http"))
+        .map(|it| it.split("?w=").next().unwrap_or(it).to_string())
+        .collect();
+    urls.dedup();
+    urls
+}
+
+#[derive(Error, Debug)]
+pub enum ImageGenerationError {
+    #[error("Failed to send image generation request")]
+    Network,
+    #[error("The prompt has been blocked by the content policy")]
+    Blocked,
+    #[error("The prompt is being reviewed")]
+    UnderReview,
+    #[error("The language of the prompt is not supported")]
+    UnsupportedLanguage,
+    #[error("Failed to get the id of the image generation request")]
+    NoRedirect,
+    #[error("No image found in the result")]
+    NoImage,
+    #[error("Timed out waiting for the images")]
+    Timeout,
+    #[error("Failed to save the image")]
+    Io(#[from] std::io::Error),
+}
+
+impl From<reqwest::Error> for ImageGenerationError {
+    fn from(_value: reqwest::Error) -> Self {
+        Self::Network
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ImageGenerationError>;
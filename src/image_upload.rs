@@ -0,0 +1,208 @@
+use std::{io::Cursor, path::PathBuf};
+
+use crate::{
+    util::new_reqwest_client, ClientSettings, ConversationMeta, ConversationStyle, CookieInFile,
+    CookieStore,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, ImageFormat};
+use reqwest::multipart::Form;
+use serde::Deserialize;
+use serde_json::json;
+use thiserror::Error;
+
+const BASE_URL: &str = "https://www.bing.com";
+
+/// Formats bing accepts, others are rejected before uploading.
+const SUPPORTED_FORMATS: [ImageFormat; 4] = [
+    ImageFormat::Jpeg,
+    ImageFormat::Png,
+    ImageFormat::Gif,
+    ImageFormat::WebP,
+];
+
+/// Where to take an image from.
+#[derive(Debug, Clone)]
+pub enum ImageSource {
+    /// Content of an image file.
+    Bytes(Vec<u8>),
+    /// Path of an image file.
+    Path(PathBuf),
+    /// Url of an image, downloaded before uploading.
+    Url(String),
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct UploadResponse {
+    #[serde(default)]
+    blob_id: String,
+    #[serde(default)]
+    processed_blob_id: String,
+}
+
+/// Upload images to bing, to be referenced in a message with
+/// [`ChatSession::send_message_with_image`](crate::ChatSession::send_message_with_image).
+#[derive(Debug, Clone)]
+pub struct ImageUploader {
+    client: reqwest::Client,
+    cookies: CookieStore,
+    base_url: String,
+    max_dimension: u32,
+}
+
+impl ImageUploader {
+    /// Create an [`ImageUploader`] with provided cookies.
+    pub fn new(cookies: &[CookieInFile]) -> Result<Self> {
+        Self::new_with_settings(cookies, &ClientSettings::default())
+    }
+
+    /// Create an [`ImageUploader`] with provided cookies, sending the requests with the
+    /// profile and through the proxy of the settings, eg. the ones of the chat session.
+    pub fn new_with_settings(cookies: &[CookieInFile], settings: &ClientSettings) -> Result<Self> {
+        Self::new_with_store(&CookieStore::new(cookies), settings)
+    }
+
+    /// Create an [`ImageUploader`] with the cookies of the store and the settings,
+    /// keeping the cookies bing sets in the store.
+    pub fn new_with_store(store: &CookieStore, settings: &ClientSettings) -> Result<Self> {
+        let client = new_reqwest_client(settings)?
+            .cookie_provider(store.jar())
+            .build()?;
+        Ok(Self {
+            client,
+            cookies: store.clone(),
+            base_url: BASE_URL.to_string(),
+            max_dimension: 1600,
+        })
+    }
+
+    /// Send the requests to `base_url` instead of `https://www.bing.com`.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Scale down images wider or taller than `max_dimension` pixels before uploading.
+    pub fn with_max_dimension(mut self, max_dimension: u32) -> Self {
+        self.max_dimension = max_dimension.max(1);
+        self
+    }
+
+    /// Upload the image, return the url to reference it in a message.
+    pub async fn upload(&self, image: ImageSource) -> Result<String> {
+        self.upload_for(image, None).await
+    }
+
+    /// Upload the image for a conversation, return the url to reference it in a message.
+    pub(crate) async fn upload_for(
+        &self,
+        image: ImageSource,
+        conversation: Option<(&ConversationMeta, ConversationStyle)>,
+    ) -> Result<String> {
+        let content = self.load(image).await?;
+        let content = prepare(&content, self.max_dimension)?;
+        let mut knowledge_request = json!({
+            "invokedSkills": ["ImageById"],
+            "subscriptionId": "Bing.Chat.Multimodal",
+            "invokedSkillsRequestData": {"enableFaceBlur": true},
+        });
+        if let Some((meta, style)) = conversation {
+            knowledge_request["convoData"] = json!({
+                "convoid": meta.conversation_id,
+                "convotone": style.tone(),
+            });
+        }
+        let form = Form::new()
+            .text(
+                "knowledgeRequest",
+                json!({"imageInfo": {}, "knowledgeRequest": knowledge_request}).to_string(),
+            )
+            .text("imageBase64", STANDARD.encode(content));
+        let request = self
+            .client
+            .post(format!("{}/images/kblob", self.base_url))
+            .header("referer", format!("{}/search?q=Bing+AI", self.base_url))
+            .multipart(form)
+            .send();
+        let response: UploadResponse = self
+            .cookies
+            .track(request)
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let blob_id = if response.processed_blob_id.is_empty() {
+            response.blob_id
+        } else {
+            response.processed_blob_id
+        };
+        if blob_id.is_empty() {
+            return Err(ImageUploadError::Rejected);
+        }
+        Ok(format!("{}/images/blob?bcid={blob_id}", self.base_url))
+    }
+
+    async fn load(&self, image: ImageSource) -> Result<Vec<u8>> {
+        match image {
+            ImageSource::Bytes(content) => Ok(content),
+            ImageSource::Path(path) => Ok(tokio::fs::read(path).await?),
+            ImageSource::Url(url) => Ok(self
+                .client
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?
+                .to_vec()),
+        }
+    }
+}
+
+/// Check the format of the image, and scale it down to fit in `max_dimension` as a jpeg.
+/// Small jpeg and png images are uploaded as is.
+fn prepare(content: &[u8], max_dimension: u32) -> Result<Vec<u8>> {
+    let format = image::guess_format(content).map_err(|_| ImageUploadError::UnsupportedFormat)?;
+    if !SUPPORTED_FORMATS.contains(&format) {
+        return Err(ImageUploadError::UnsupportedFormat);
+    }
+    let decoded = image::load_from_memory_with_format(content, format)
+        .map_err(|_| ImageUploadError::Decode)?;
+    let fits = decoded.width() <= max_dimension && decoded.height() <= max_dimension;
+    if fits && matches!(format, ImageFormat::Jpeg | ImageFormat::Png) {
+        return Ok(content.to_vec());
+    }
+    let resized = if fits {
+        decoded
+    } else {
+        decoded.resize(max_dimension, max_dimension, FilterType::Triangle)
+    };
+    let mut encoded = Cursor::new(vec![]);
+    JpegEncoder::new_with_quality(&mut encoded, 85)
+        .encode_image(&resized.to_rgb8())
+        .map_err(|_| ImageUploadError::Decode)?;
+    Ok(encoded.into_inner())
+}
+
+#[derive(Error, Debug)]
+pub enum ImageUploadError {
+    #[error("Failed to send image upload request")]
+    Network,
+    #[error("Only jpeg, png, gif and webp images are supported")]
+    UnsupportedFormat,
+    #[error("Failed to decode the image")]
+    Decode,
+    #[error("The image has been rejected")]
+    Rejected,
+    #[error("Failed to read the image")]
+    Io(#[from] std::io::Error),
+}
+
+impl From<reqwest::Error> for ImageUploadError {
+    fn from(_value: reqwest::Error) -> Self {
+        Self::Network
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ImageUploadError>;
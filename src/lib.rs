@@ -2,17 +2,63 @@ pub use futures_util::StreamExt;
 use serde::Deserialize;
 use serde::Serialize;
 
+mod account_pool;
+mod batch;
+mod context;
+mod conversation_manager;
 mod conversation_meta;
+mod cookies;
+mod image;
+mod image_upload;
+mod metrics;
+#[cfg(feature = "server")]
+mod openai;
+mod profile;
+mod rate_limit;
+mod recorder;
+mod render;
 mod session;
+mod settings;
+mod trace;
+mod transport;
+pub use account_pool::{
+    Account, AccountHealth, AccountPool, AccountPoolError, Result as AccountPoolResult, Strategy,
+};
+pub use batch::{BatchError, BatchItem, BatchRunner, Result as BatchResult};
+pub use context::DocumentContext;
+pub use conversation_manager::{
+    ConversationList, ConversationManager, ConversationManagingError, ConversationSummary,
+    HistoryMessage, Result as ConversationManagingResult,
+};
 pub use conversation_meta::{
     ConversationMeta, ConversationMetaCreatingError, Result as ConversationMetaCreatingResult,
 };
+pub use cookies::{CookieError, CookieStore, Result as CookieResult};
+pub use image::{ImageGenerationError, ImageGenerator, Result as ImageGenerationResult};
+pub use image_upload::{ImageSource, ImageUploadError, ImageUploader, Result as ImageUploadResult};
+pub use metrics::{ChatMetrics, TurnMetrics};
+#[cfg(feature = "server")]
+pub use openai::{OpenAiServer, CONVERSATION_HEADER};
+pub use profile::{ClientProfile, InvalidProfile, Result as ProfileResult};
+pub use rate_limit::{
+    Action, Rate, RateLimited, RateLimiter, Result as RateLimitResult, WhenLimited,
+};
+pub use recorder::{FrameDirection, FrameObserver, FrameRecorder, RecordedFrame};
+pub use render::RenderFormat;
 pub use session::{
-    ChatError, ChatSession, ConversationStyle, NewBingResponseMessage, Result as SessionResult,
+    ChatError, ChatEvent, ChatEventStream, ChatSession, ChatStream, ConversationStyle,
+    NewBingResponseMessage, ParseConversationStyleError, ParseWarning, ResponsePart,
+    Result as SessionResult, SearchResult, SuggestedResponse, TextDelta, Throttling, Turn,
+    TurnResult,
+};
+pub use settings::{
+    ClientSettings, Endpoints, ForwardedFor, GeoLocation, Locale, ParseMode, PromptLimit,
+    SettingsError, WhenTooLong,
 };
+pub use transport::{Connection, ReplayTransport, Transport, WebSocketTransport};
 mod util;
 /// Fields we care about in a Cookie file.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct CookieInFile {
     /// Name of the cookie.
     pub name: String,
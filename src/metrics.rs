@@ -0,0 +1,38 @@
+use std::{fmt, time::Duration};
+
+use crate::ChatError;
+
+/// Measurements of a finished turn.
+#[derive(Debug, Clone, Copy)]
+pub struct TurnMetrics {
+    /// from connecting to the chat hub to the first text of the answer,
+    /// `None` if no text was received.
+    pub time_to_first_token: Option<Duration>,
+    /// from connecting to the chat hub to the end of the answer.
+    pub latency: Duration,
+    /// SignalR records received, several can arrive in one websocket message.
+    pub frames: usize,
+    /// bytes of the records received, with their delimiters.
+    pub bytes: usize,
+}
+
+/// Hooks receiving the measurements of chatting, eg. to export them to a metrics system.
+///
+/// Attach it to [`ClientSettings::metrics`](crate::ClientSettings::metrics),
+/// all methods do nothing by default.
+pub trait ChatMetrics: fmt::Debug + Send + Sync {
+    /// The first text of an answer arrived.
+    fn record_time_to_first_token(&self, _latency: Duration) {}
+
+    /// A turn finished with an answer.
+    fn record_turn(&self, _metrics: &TurnMetrics) {}
+
+    /// A request is tried again after a failure: creating a conversation with another account
+    /// of an [`AccountPool`](crate::AccountPool), or a [`BatchRunner`](crate::BatchRunner)
+    /// starting a new conversation or resending a prompt that failed in an earlier run.
+    fn record_retry(&self) {}
+
+    /// Sending a message or receiving the answer failed,
+    /// [`ChatError::kind`] names the variant for counting.
+    fn record_error(&self, _error: &ChatError) {}
+}
@@ -0,0 +1,333 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    convert::Infallible,
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{ChatSession, ClientSettings, ConversationStyle, CookieInFile, TextDelta};
+use async_stream::stream;
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Json, Router,
+};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::mpsc;
+
+/// Header choosing the stored conversation a request continues,
+/// without it the conversation is found by the message history.
+pub const CONVERSATION_HEADER: &str = "x-conversation-id";
+
+#[derive(Debug)]
+struct StoredSession {
+    session: ChatSession,
+    stored: Instant,
+}
+
+/// State of the OpenAI compatible server: the account to chat with,
+/// and the conversations in progress.
+///
+/// At most 1000 conversations are kept, for an hour after their last message by default,
+/// see [`with_max_sessions`](OpenAiServer::with_max_sessions) and
+/// [`with_idle_timeout`](OpenAiServer::with_idle_timeout).
+#[derive(Debug)]
+pub struct OpenAiServer {
+    cookies: Vec<CookieInFile>,
+    settings: ClientSettings,
+    sessions: Mutex<HashMap<String, StoredSession>>,
+    max_sessions: usize,
+    idle_timeout: Duration,
+}
+
+impl OpenAiServer {
+    /// Create an [`OpenAiServer`] creating conversations with the cookies and settings.
+    pub fn new(cookies: Vec<CookieInFile>, settings: ClientSettings) -> Self {
+        Self {
+            cookies,
+            settings,
+            sessions: Mutex::new(HashMap::new()),
+            max_sessions: 1000,
+            idle_timeout: Duration::from_secs(3600),
+        }
+    }
+
+    /// Keep at most `max_sessions` conversations, forgetting the least recently used ones.
+    pub fn with_max_sessions(mut self, max_sessions: usize) -> Self {
+        self.max_sessions = max_sessions.max(1);
+        self
+    }
+
+    /// Forget the conversations without a message for `idle_timeout`.
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Routes of the server, `/v1/chat/completions` and `/v1/models`.
+    pub fn router(self: Arc<Self>) -> Router {
+        Router::new()
+            .route("/v1/chat/completions", post(chat_completions))
+            .route("/v1/models", get(models))
+            .with_state(self)
+    }
+
+    async fn take_session(
+        &self,
+        key: &str,
+        style: ConversationStyle,
+    ) -> std::result::Result<(ChatSession, bool), ApiError> {
+        let stored = self.sessions.lock().unwrap().remove(key);
+        if let Some(stored) = stored.filter(|it| it.stored.elapsed() < self.idle_timeout) {
+            return Ok((stored.session, false));
+        }
+        let session =
+            ChatSession::create_with_settings(style, &self.cookies, self.settings.clone()).await?;
+        Ok((session, true))
+    }
+
+    fn store_session(&self, key: String, session: ChatSession) {
+        let mut sessions = self.sessions.lock().unwrap();
+        let now = Instant::now();
+        sessions.retain(|_, it| now.duration_since(it.stored) < self.idle_timeout);
+        sessions.insert(
+            key,
+            StoredSession {
+                session,
+                stored: now,
+            },
+        );
+        if sessions.len() > self.max_sessions {
+            let oldest = sessions
+                .iter()
+                .min_by_key(|(_, it)| it.stored)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                sessions.remove(&oldest);
+            }
+        }
+    }
+}
+
+const MODELS: [(&str, ConversationStyle); 3] = [
+    ("bing-creative", ConversationStyle::Creative),
+    ("bing-balanced", ConversationStyle::Balanced),
+    ("bing-precise", ConversationStyle::Precise),
+];
+
+/// Map a model name to a conversation style, eg. "bing-creative" to [`ConversationStyle::Creative`],
+/// unknown models are [`ConversationStyle::Balanced`].
+fn style_of_model(model: &str) -> ConversationStyle {
+    let model = model.to_lowercase();
+    MODELS
+        .iter()
+        .find(|(name, _)| model.contains(name.trim_start_matches("bing-")))
+        .map(|(_, style)| *style)
+        .unwrap_or(ConversationStyle::Balanced)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Hash)]
+struct Message {
+    role: String,
+    content: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatCompletionRequest {
+    model: String,
+    messages: Vec<Message>,
+    #[serde(default)]
+    stream: bool,
+}
+
+fn history_key(messages: &[Message]) -> String {
+    let mut hasher = DefaultHasher::new();
+    messages.hash(&mut hasher);
+    format!("history-{:016x}", hasher.finish())
+}
+
+/// The text to send: the last user message, with the earlier messages as context
+/// when bing hasn't seen them.
+fn prompt(messages: &[Message], is_new_session: bool) -> String {
+    let Some((last, earlier)) = messages.split_last() else {
+        return String::new();
+    };
+    if !is_new_session || earlier.is_empty() {
+        return last.content.clone();
+    }
+    let mut prompt = String::new();
+    for message in earlier {
+        prompt.push_str(&format!(
+            "[{}](#message)\n{}\n\n",
+            message.role, message.content
+        ));
+    }
+    prompt.push_str(&last.content);
+    prompt
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|it| it.as_secs())
+        .unwrap_or(0)
+}
+
+async fn models() -> impl IntoResponse {
+    let data: Vec<_> = MODELS
+        .iter()
+        .map(|(name, _)| json!({"id": name, "object": "model", "owned_by": "bing"}))
+        .collect();
+    Json(json!({"object": "list", "data": data}))
+}
+
+async fn chat_completions(
+    State(server): State<Arc<OpenAiServer>>,
+    headers: HeaderMap,
+    Json(request): Json<ChatCompletionRequest>,
+) -> std::result::Result<Response, ApiError> {
+    if request.messages.is_empty() {
+        return Err(ApiError::BadRequest("messages must not be empty"));
+    }
+    let by_header = headers
+        .get(CONVERSATION_HEADER)
+        .and_then(|it| it.to_str().ok())
+        .map(ToString::to_string);
+    let key = by_header
+        .clone()
+        .unwrap_or_else(|| history_key(&request.messages[..request.messages.len() - 1]));
+    let (mut session, is_new_session) = server
+        .take_session(&key, style_of_model(&request.model))
+        .await?;
+    let prompt = prompt(&request.messages, is_new_session);
+    let mut chat_stream = session.chat_stream(&prompt).await?;
+
+    let id = format!("chatcmpl-{}", uuid::Uuid::new_v4().simple());
+    let created = unix_time();
+    let model = request.model.clone();
+    let mut messages = request.messages;
+    // the key to store the session under once the reply is known
+    let next_key = move |reply: &str| {
+        by_header.unwrap_or_else(|| {
+            messages.push(Message {
+                role: "assistant".to_string(),
+                content: reply.to_string(),
+            });
+            history_key(&messages)
+        })
+    };
+
+    if !request.stream {
+        let mut reply = String::new();
+        while let Some(response) = chat_stream.next().await {
+            let response = response?;
+            if !response.text.is_empty() {
+                reply = response.text;
+            }
+        }
+        server.store_session(next_key(&reply), session);
+        return Ok(Json(json!({
+            "id": id,
+            "object": "chat.completion",
+            "created": created,
+            "model": model,
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": reply},
+                "finish_reason": "stop",
+            }],
+        }))
+        .into_response());
+    }
+
+    let chunk = move |delta: serde_json::Value, finish_reason: Option<&str>| {
+        Event::default().data(
+            json!({
+                "id": id,
+                "object": "chat.completion.chunk",
+                "created": created,
+                "model": model,
+                "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}],
+            })
+            .to_string(),
+        )
+    };
+    // the turn is finished and stored in a task of its own, even if the client disconnects
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let _ = sender.send(chunk(json!({"role": "assistant"}), None));
+        let mut reply = String::new();
+        while let Some(response) = chat_stream.next().await {
+            let response = match response {
+                Ok(response) => response,
+                Err(e) => {
+                    // the conversation may be unusable, it is not stored
+                    let _ = sender.send(Event::default().data(error_body(&e).to_string()));
+                    let _ = sender.send(Event::default().data("[DONE]"));
+                    return;
+                }
+            };
+            let text = response.text.trim();
+            // a rewritten answer cannot be taken back, the new one is sent whole
+            let (TextDelta::Append(delta) | TextDelta::Rewrite(delta)) =
+                TextDelta::between(&reply, text);
+            if !delta.is_empty() {
+                let _ = sender.send(chunk(json!({"content": delta}), None));
+                reply = text.to_string();
+            }
+        }
+        server.store_session(next_key(&reply), session);
+        let _ = sender.send(chunk(json!({}), Some("stop")));
+        let _ = sender.send(Event::default().data("[DONE]"));
+    });
+    let events = stream! {
+        while let Some(event) = receiver.recv().await {
+            yield event;
+        }
+    };
+    Ok(Sse::new(events.map(Ok::<_, Infallible>)).into_response())
+}
+
+/// Errors reported in the format of the OpenAI API.
+#[derive(Debug)]
+enum ApiError {
+    BadRequest(&'static str),
+    Create(crate::ConversationMetaCreatingError),
+    Chat(crate::ChatError),
+}
+
+impl From<crate::ConversationMetaCreatingError> for ApiError {
+    fn from(value: crate::ConversationMetaCreatingError) -> Self {
+        Self::Create(value)
+    }
+}
+
+impl From<crate::ChatError> for ApiError {
+    fn from(value: crate::ChatError) -> Self {
+        Self::Chat(value)
+    }
+}
+
+/// Body of an error in the format of the OpenAI API.
+fn error_body(message: &impl ToString) -> serde_json::Value {
+    json!({"error": {"message": message.to_string(), "type": "api_error"}})
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, message.to_string()),
+            ApiError::Create(e) => (StatusCode::BAD_GATEWAY, e.to_string()),
+            ApiError::Chat(e) => (StatusCode::BAD_GATEWAY, e.to_string()),
+        };
+        (status, Json(error_body(&message))).into_response()
+    }
+}
@@ -0,0 +1,169 @@
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The browser a client pretends to be: user agent, client hints and platform,
+/// which should be consistent with each other.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ClientProfile {
+    /// `user-agent`
+    pub user_agent: String,
+    /// `sec-ch-ua`, the brands with major versions.
+    pub brands: String,
+    /// `sec-ch-ua-full-version-list`, the brands with full versions.
+    pub full_version_list: String,
+    /// `sec-ch-ua-full-version`, full version of the browser.
+    pub full_version: String,
+    /// `sec-ch-ua-arch`, eg. "x86" or "arm".
+    pub arch: String,
+    /// `sec-ch-ua-bitness`, eg. "64".
+    pub bitness: String,
+    /// `sec-ch-ua-mobile`
+    pub mobile: bool,
+    /// `sec-ch-ua-model`, device model on mobile platforms.
+    pub model: String,
+    /// `sec-ch-ua-platform`, eg. "Windows".
+    pub platform: String,
+    /// `sec-ch-ua-platform-version`
+    pub platform_version: String,
+    /// `x-ms-useragent`, the sdk bing's web page uses.
+    pub ms_user_agent: String,
+}
+
+impl ClientProfile {
+    /// Edge 110 on Windows 11.
+    pub fn edge_110_windows() -> Self {
+        Self {
+            user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/110.0.0.0 Safari/537.36 Edg/110.0.1587.69".to_string(),
+            brands: "\"Chromium\";v=\"110\", \"Not A(Brand\";v=\"24\", \"Microsoft Edge\";v=\"110\"".to_string(),
+            full_version_list: "\"Chromium\";v=\"110.0.5481.192\", \"Not A(Brand\";v=\"24.0.0.0\", \"Microsoft Edge\";v=\"110.0.1587.69\"".to_string(),
+            full_version: "110.0.1587.69".to_string(),
+            arch: "x86".to_string(),
+            bitness: "64".to_string(),
+            mobile: false,
+            model: String::new(),
+            platform: "Windows".to_string(),
+            platform_version: "15.0.0".to_string(),
+            ms_user_agent: "azsdk-js-api-client-factory/1.0.0-beta.1 core-rest-pipeline/1.10.0 OS/Win32".to_string(),
+        }
+    }
+
+    /// Edge 114 on Windows 11.
+    pub fn edge_114_windows() -> Self {
+        Self {
+            user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/114.0.0.0 Safari/537.36 Edg/114.0.1823.51".to_string(),
+            brands: "\"Not.A/Brand\";v=\"8\", \"Chromium\";v=\"114\", \"Microsoft Edge\";v=\"114\"".to_string(),
+            full_version_list: "\"Not.A/Brand\";v=\"8.0.0.0\", \"Chromium\";v=\"114.0.5735.134\", \"Microsoft Edge\";v=\"114.0.1823.51\"".to_string(),
+            full_version: "114.0.1823.51".to_string(),
+            arch: "x86".to_string(),
+            bitness: "64".to_string(),
+            mobile: false,
+            model: String::new(),
+            platform: "Windows".to_string(),
+            platform_version: "15.0.0".to_string(),
+            ms_user_agent: "azsdk-js-api-client-factory/1.0.0-beta.1 core-rest-pipeline/1.10.0 OS/Win32".to_string(),
+        }
+    }
+
+    /// Edge 114 on macOS Ventura with Apple silicon.
+    pub fn edge_114_macos() -> Self {
+        Self {
+            user_agent: "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/114.0.0.0 Safari/537.36 Edg/114.0.1823.51".to_string(),
+            brands: "\"Not.A/Brand\";v=\"8\", \"Chromium\";v=\"114\", \"Microsoft Edge\";v=\"114\"".to_string(),
+            full_version_list: "\"Not.A/Brand\";v=\"8.0.0.0\", \"Chromium\";v=\"114.0.5735.134\", \"Microsoft Edge\";v=\"114.0.1823.51\"".to_string(),
+            full_version: "114.0.1823.51".to_string(),
+            arch: "arm".to_string(),
+            bitness: "64".to_string(),
+            mobile: false,
+            model: String::new(),
+            platform: "macOS".to_string(),
+            platform_version: "13.4.0".to_string(),
+            ms_user_agent: "azsdk-js-api-client-factory/1.0.0-beta.1 core-rest-pipeline/1.10.0 OS/MacIntel".to_string(),
+        }
+    }
+
+    /// Edge 114 on Android 13.
+    pub fn edge_114_android() -> Self {
+        Self {
+            user_agent: "Mozilla/5.0 (Linux; Android 13; Pixel 7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/114.0.0.0 Mobile Safari/537.36 EdgA/114.0.1823.43".to_string(),
+            brands: "\"Not.A/Brand\";v=\"8\", \"Chromium\";v=\"114\", \"Microsoft Edge\";v=\"114\"".to_string(),
+            full_version_list: "\"Not.A/Brand\";v=\"8.0.0.0\", \"Chromium\";v=\"114.0.5735.131\", \"Microsoft Edge\";v=\"114.0.1823.43\"".to_string(),
+            full_version: "114.0.1823.43".to_string(),
+            arch: String::new(),
+            bitness: String::new(),
+            mobile: true,
+            model: "Pixel 7".to_string(),
+            platform: "Android".to_string(),
+            platform_version: "13.0.0".to_string(),
+            ms_user_agent: "azsdk-js-api-client-factory/1.0.0-beta.1 core-rest-pipeline/1.10.0 OS/Android".to_string(),
+        }
+    }
+
+    /// Put the user agent and client hints of the profile into `headers`,
+    /// fail if one of them is not a valid header value, eg. contains a line break.
+    pub(crate) fn apply(&self, headers: &mut HeaderMap) -> Result<()> {
+        let quoted = |value: &str| format!("\"{value}\"");
+        let values = [
+            (USER_AGENT, self.user_agent.clone()),
+            (HeaderName::from_static("sec-ch-ua"), self.brands.clone()),
+            (
+                HeaderName::from_static("sec-ch-ua-full-version-list"),
+                self.full_version_list.clone(),
+            ),
+            (
+                HeaderName::from_static("sec-ch-ua-full-version"),
+                quoted(&self.full_version),
+            ),
+            (
+                HeaderName::from_static("sec-ch-ua-arch"),
+                quoted(&self.arch),
+            ),
+            (
+                HeaderName::from_static("sec-ch-ua-bitness"),
+                quoted(&self.bitness),
+            ),
+            (
+                HeaderName::from_static("sec-ch-ua-mobile"),
+                if self.mobile { "?1" } else { "?0" }.to_string(),
+            ),
+            (
+                HeaderName::from_static("sec-ch-ua-model"),
+                quoted(&self.model),
+            ),
+            (
+                HeaderName::from_static("sec-ch-ua-platform"),
+                quoted(&self.platform),
+            ),
+            (
+                HeaderName::from_static("sec-ch-ua-platform-version"),
+                quoted(&self.platform_version),
+            ),
+            (
+                HeaderName::from_static("x-ms-useragent"),
+                self.ms_user_agent.clone(),
+            ),
+        ];
+        for (name, value) in values {
+            let value = HeaderValue::from_str(&value).map_err(|_| InvalidProfile {
+                header: name.to_string(),
+            })?;
+            headers.insert(name, value);
+        }
+        Ok(())
+    }
+}
+
+impl Default for ClientProfile {
+    fn default() -> Self {
+        Self::edge_110_windows()
+    }
+}
+
+#[derive(Error, Debug, Clone)]
+#[error("Invalid value of the {header} header in the client profile")]
+pub struct InvalidProfile {
+    /// Name of the header, eg. "user-agent".
+    pub header: String,
+}
+
+pub type Result<T> = std::result::Result<T, InvalidProfile>;
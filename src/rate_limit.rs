@@ -0,0 +1,388 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::time::Instant;
+
+use crate::SettingsError;
+
+/// Requests limited by a [`RateLimiter`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Creating a conversation.
+    CreateConversation,
+    /// Sending a message in a conversation, ie. opening a hub connection.
+    SendMessage,
+}
+
+/// At most `count` requests in `period`, allowing bursts of `count` requests,
+/// both should be non zero, see [`validate`](Rate::validate).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(try_from = "RateFields")]
+pub struct Rate {
+    /// Requests allowed in a period, also the size of the bucket.
+    pub count: u32,
+    /// Length of a period.
+    pub period: Duration,
+}
+
+/// The fields of a [`Rate`], validated after deserializing.
+#[derive(Deserialize)]
+struct RateFields {
+    count: u32,
+    period: Duration,
+}
+
+impl TryFrom<RateFields> for Rate {
+    type Error = SettingsError;
+
+    fn try_from(fields: RateFields) -> std::result::Result<Self, Self::Error> {
+        Self::new(fields.count, fields.period)
+    }
+}
+
+impl Rate {
+    /// At most `count` requests in `period`, fail if either is zero.
+    pub fn new(count: u32, period: Duration) -> std::result::Result<Self, SettingsError> {
+        let rate = Self { count, period };
+        rate.validate()?;
+        Ok(rate)
+    }
+
+    /// Fail for a zero `count` or `period`, such a rate would never allow a request.
+    pub fn validate(&self) -> std::result::Result<(), SettingsError> {
+        if self.count == 0 || self.period.is_zero() {
+            return Err(SettingsError::InvalidRate {
+                count: self.count,
+                period: self.period,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// What to do when a request exceeds the limit.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub enum WhenLimited {
+    /// Wait until the request is allowed.
+    #[default]
+    Wait,
+    /// Fail the request with [`RateLimited`].
+    Reject,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: Rate, now: Instant) -> Self {
+        Self {
+            tokens: f64::from(rate.count),
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, rate: Rate, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        let refilled = elapsed * f64::from(rate.count) / rate.period.as_secs_f64();
+        self.tokens = (self.tokens + refilled).min(f64::from(rate.count));
+        self.updated = now;
+    }
+
+    /// How long until a token is available.
+    fn wait_time(&self, rate: Rate) -> Duration {
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            rate.period
+                .mul_f64((1.0 - self.tokens) / f64::from(rate.count))
+        }
+    }
+}
+
+/// Client side token bucket limits of requests to bing,
+/// both globally and per [`Account`](crate::Account).
+///
+/// Attach it to [`ClientSettings::rate_limiter`](crate::ClientSettings::rate_limiter)
+/// to limit the conversations created and messages sent with the settings.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    global: HashMap<Action, Rate>,
+    per_account: HashMap<Action, Rate>,
+    when_limited: WhenLimited,
+    buckets: Mutex<HashMap<(Option<String>, Action), TokenBucket>>,
+}
+
+impl RateLimiter {
+    /// Create a [`RateLimiter`] without any limit.
+    pub fn new(when_limited: WhenLimited) -> Self {
+        Self {
+            when_limited,
+            ..Self::default()
+        }
+    }
+
+    /// Limit the action across all accounts, fail if the rate is invalid, see [`Rate::validate`].
+    pub fn with_global_limit(
+        mut self,
+        action: Action,
+        rate: Rate,
+    ) -> std::result::Result<Self, SettingsError> {
+        rate.validate()?;
+        self.global.insert(action, rate);
+        Ok(self)
+    }
+
+    /// Limit the action for each account separately, fail if the rate is invalid,
+    /// see [`Rate::validate`].
+    pub fn with_account_limit(
+        mut self,
+        action: Action,
+        rate: Rate,
+    ) -> std::result::Result<Self, SettingsError> {
+        rate.validate()?;
+        self.per_account.insert(action, rate);
+        Ok(self)
+    }
+
+    /// Take a token for the action done with the account,
+    /// waiting for it or failing depending on [`WhenLimited`].
+    pub async fn acquire(&self, action: Action, account: Option<&str>) -> Result<()> {
+        loop {
+            let wait = self.try_acquire(action, account);
+            if wait.is_zero() {
+                return Ok(());
+            }
+            match self.when_limited {
+                WhenLimited::Wait => tokio::time::sleep(wait).await,
+                WhenLimited::Reject => return Err(RateLimited { retry_after: wait }),
+            }
+        }
+    }
+
+    /// Take a token if every bucket involved has one,
+    /// otherwise return how long to wait before trying again.
+    fn try_acquire(&self, action: Action, account: Option<&str>) -> Duration {
+        let now = Instant::now();
+        let mut limits = vec![];
+        if let Some(rate) = self.global.get(&action) {
+            limits.push(((None, action), *rate));
+        }
+        if let (Some(rate), Some(account)) = (self.per_account.get(&action), account) {
+            limits.push(((Some(account.to_string()), action), *rate));
+        }
+        let mut buckets = self.buckets.lock().unwrap();
+        let wait = limits
+            .iter()
+            .map(|(key, rate)| {
+                let bucket = buckets
+                    .entry(key.clone())
+                    .or_insert_with(|| TokenBucket::new(*rate, now));
+                bucket.refill(*rate, now);
+                bucket.wait_time(*rate)
+            })
+            .max()
+            .unwrap_or(Duration::ZERO);
+        if wait.is_zero() {
+            for (key, _) in &limits {
+                if let Some(bucket) = buckets.get_mut(key) {
+                    bucket.tokens -= 1.0;
+                }
+            }
+        }
+        wait
+    }
+}
+
+#[derive(Error, Debug, Clone, Copy)]
+#[error("Rate limited, retry after {retry_after:?}")]
+pub struct RateLimited {
+    /// How long until the request would be allowed.
+    pub retry_after: Duration,
+}
+
+pub type Result<T> = std::result::Result<T, RateLimited>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(count: u32, secs: u64) -> Rate {
+        Rate::new(count, Duration::from_secs(secs)).unwrap()
+    }
+
+    fn limiter(when_limited: WhenLimited) -> RateLimiter {
+        RateLimiter::new(when_limited)
+            .with_global_limit(Action::SendMessage, rate(3, 60))
+            .unwrap()
+    }
+
+    /// Equal but for rounding errors.
+    fn assert_close(actual: Duration, expected: Duration) {
+        let diff = actual.max(expected) - actual.min(expected);
+        assert!(
+            diff < Duration::from_millis(1),
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    fn assert_retry_after(result: Result<()>, expected: Duration) {
+        assert_close(result.unwrap_err().retry_after, expected);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn burst_up_to_count() {
+        let limiter = limiter(WhenLimited::Reject);
+        for _ in 0..3 {
+            limiter.acquire(Action::SendMessage, None).await.unwrap();
+        }
+        assert_retry_after(
+            limiter.acquire(Action::SendMessage, None).await,
+            Duration::from_secs(20),
+        );
+        // other actions are not limited
+        for _ in 0..10 {
+            limiter
+                .acquire(Action::CreateConversation, None)
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refill() {
+        let limiter = limiter(WhenLimited::Wait);
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire(Action::SendMessage, None).await.unwrap();
+        }
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        limiter.acquire(Action::SendMessage, None).await.unwrap();
+        assert_close(start.elapsed(), Duration::from_secs(20));
+        limiter.acquire(Action::SendMessage, None).await.unwrap();
+        assert_close(start.elapsed(), Duration::from_secs(40));
+
+        // a full bucket after a long idle time, but no more than `count`
+        tokio::time::advance(Duration::from_secs(600)).await;
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire(Action::SendMessage, None).await.unwrap();
+        }
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        limiter.acquire(Action::SendMessage, None).await.unwrap();
+        assert!(start.elapsed() > Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn accounts_are_isolated() {
+        let limiter = RateLimiter::new(WhenLimited::Reject)
+            .with_account_limit(Action::SendMessage, rate(1, 60))
+            .unwrap();
+        limiter
+            .acquire(Action::SendMessage, Some("a"))
+            .await
+            .unwrap();
+        assert!(limiter
+            .acquire(Action::SendMessage, Some("a"))
+            .await
+            .is_err());
+        limiter
+            .acquire(Action::SendMessage, Some("b"))
+            .await
+            .unwrap();
+        // without an account only the global limit applies
+        limiter.acquire(Action::SendMessage, None).await.unwrap();
+        limiter.acquire(Action::SendMessage, None).await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn global_and_account_limits_combine() {
+        let limiter = RateLimiter::new(WhenLimited::Reject)
+            .with_global_limit(Action::SendMessage, rate(2, 60))
+            .unwrap()
+            .with_account_limit(Action::SendMessage, rate(1, 60))
+            .unwrap();
+        limiter
+            .acquire(Action::SendMessage, Some("a"))
+            .await
+            .unwrap();
+        limiter
+            .acquire(Action::SendMessage, Some("b"))
+            .await
+            .unwrap();
+        // "c" has a token, but the global bucket is empty
+        assert_retry_after(
+            limiter.acquire(Action::SendMessage, Some("c")).await,
+            Duration::from_secs(30),
+        );
+        // a rejected request takes no token from "c"
+        tokio::time::advance(Duration::from_secs(30)).await;
+        limiter
+            .acquire(Action::SendMessage, Some("c"))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retry_after_shrinks_while_refilling() {
+        let limiter = RateLimiter::new(WhenLimited::Reject)
+            .with_global_limit(Action::SendMessage, rate(1, 60))
+            .unwrap();
+        limiter.acquire(Action::SendMessage, None).await.unwrap();
+        assert_retry_after(
+            limiter.acquire(Action::SendMessage, None).await,
+            Duration::from_secs(60),
+        );
+        tokio::time::advance(Duration::from_secs(15)).await;
+        assert_retry_after(
+            limiter.acquire(Action::SendMessage, None).await,
+            Duration::from_secs(45),
+        );
+        tokio::time::advance(Duration::from_secs(45)).await;
+        limiter.acquire(Action::SendMessage, None).await.unwrap();
+    }
+
+    #[test]
+    fn zero_count_is_rejected() {
+        assert!(matches!(
+            Rate::new(0, Duration::from_secs(60)),
+            Err(SettingsError::InvalidRate { count: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn zero_period_is_rejected() {
+        assert!(matches!(
+            Rate::new(1, Duration::ZERO),
+            Err(SettingsError::InvalidRate { period, .. }) if period.is_zero()
+        ));
+    }
+
+    #[test]
+    fn zero_count_limit_is_rejected() {
+        let rate = Rate {
+            count: 0,
+            period: Duration::from_secs(60),
+        };
+        let result =
+            RateLimiter::new(WhenLimited::Wait).with_account_limit(Action::SendMessage, rate);
+        assert!(matches!(result, Err(SettingsError::InvalidRate { .. })));
+    }
+
+    #[test]
+    fn zero_count_is_not_deserialized() {
+        let result: std::result::Result<Rate, _> =
+            serde_json::from_str(r#"{"count": 0, "period": {"secs": 60, "nanos": 0}}"#);
+        let error = result.unwrap_err().to_string();
+        assert!(
+            error.starts_with("A rate should allow at least one request"),
+            "{error}"
+        );
+        let rate: Rate =
+            serde_json::from_str(r#"{"count": 2, "period": {"secs": 60, "nanos": 0}}"#).unwrap();
+        assert_eq!(rate.count, 2);
+    }
+}
@@ -0,0 +1,93 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, LineWriter, Write},
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{session::parse_record, ChatEvent, SessionResult};
+
+/// Whether a SignalR record was sent to or received from the chat hub.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameDirection {
+    /// sent to the chat hub.
+    Sent,
+    /// received from the chat hub.
+    Received,
+}
+
+/// Observer of the raw SignalR records exchanged with the chat hub, eg. to debug protocol changes.
+///
+/// Attach it to [`ClientSettings::frame_observer`](crate::ClientSettings::frame_observer).
+pub trait FrameObserver: fmt::Debug + Send + Sync {
+    /// A record, the json text without the `0x1e` delimiter, was sent or received.
+    fn on_frame(&self, direction: FrameDirection, record: &str);
+}
+
+/// A record written by a [`FrameRecorder`].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedFrame {
+    /// sent or received.
+    pub direction: FrameDirection,
+    /// milliseconds since the unix epoch.
+    pub time: u64,
+    /// the record as sent or received.
+    pub record: String,
+}
+
+impl RecordedFrame {
+    /// Parse a received record like [`ChatSession`](crate::ChatSession) does,
+    /// `None` for records without an event, eg. pings.
+    /// [`ChatEvent::Completed`] gathers several records and is never returned.
+    pub fn parse(&self) -> SessionResult<Option<ChatEvent>> {
+        parse_record(&self.record)
+    }
+}
+
+/// A [`FrameObserver`] writing each record as a line of json, see [`RecordedFrame`].
+#[derive(Debug)]
+pub struct FrameRecorder {
+    file: Mutex<LineWriter<File>>,
+}
+
+impl FrameRecorder {
+    /// Record into the file, appending to it if it exists.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::options().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(LineWriter::new(file)),
+        })
+    }
+
+    /// Read the records written to the file.
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<RecordedFrame>> {
+        BufReader::new(File::open(path)?)
+            .lines()
+            .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect()
+    }
+}
+
+impl FrameObserver for FrameRecorder {
+    fn on_frame(&self, direction: FrameDirection, record: &str) {
+        let frame = RecordedFrame {
+            direction,
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|it| it.as_millis() as u64)
+                .unwrap_or(0),
+            record: record.to_string(),
+        };
+        let Ok(line) = serde_json::to_string(&frame) else {
+            return;
+        };
+        // recording must not break chatting, a failed write only loses the record
+        let _ = writeln!(self.file.lock().unwrap(), "{line}");
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt::Write;
+
+use crate::NewBingResponseMessage;
+
+/// Output formats of [`NewBingResponseMessage::render`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderFormat {
+    /// Markdown, with `[^1]` footnotes linking to the sources.
+    Markdown,
+    /// The text without footnote markers, nor the list of sources.
+    PlainText,
+    /// Html, with superscript links to the sources.
+    Html,
+    /// Text for terminals, with OSC-8 hyperlinks to the sources.
+    Ansi,
+}
+
+/// A piece of the text, split at the `[^1^]` footnote markers.
+enum Segment<'a> {
+    Text(&'a str),
+    Footnote(usize),
+}
+
+impl NewBingResponseMessage {
+    /// Render the text with the `[^1^]` markers referring to
+    /// [`source_attributions`](NewBingResponseMessage::source_attributions) rewritten,
+    /// followed by the list of sources.
+    /// A marker cut off at the end of a partly streamed message is left out.
+    pub fn render(&self, format: RenderFormat) -> String {
+        let source = |index: usize| {
+            index
+                .checked_sub(1)
+                .and_then(|it| self.source_attributions.get(it))
+        };
+        let mut rendered = String::new();
+        for segment in segments(&self.text) {
+            match (segment, format) {
+                (Segment::Text(text), RenderFormat::Html) => {
+                    rendered.push_str(&escape_html(text).replace('\n', "<br>\n"))
+                }
+                (Segment::Text(text), _) => rendered.push_str(text),
+                (Segment::Footnote(_), RenderFormat::PlainText) => {}
+                (Segment::Footnote(index), RenderFormat::Markdown) => {
+                    write!(rendered, "[^{index}]").unwrap();
+                }
+                (Segment::Footnote(index), RenderFormat::Html) => match source(index) {
+                    Some(url) => write!(
+                        rendered,
+                        r#"<sup><a href="{}">[{index}]</a></sup>"#,
+                        escape_html(url)
+                    )
+                    .unwrap(),
+                    None => write!(rendered, "<sup>[{index}]</sup>").unwrap(),
+                },
+                (Segment::Footnote(index), RenderFormat::Ansi) => match source(index) {
+                    Some(url) => rendered.push_str(&hyperlink(url, &format!("[{index}]"))),
+                    None => write!(rendered, "[{index}]").unwrap(),
+                },
+            }
+        }
+        if self.source_attributions.is_empty() {
+            return rendered;
+        }
+        match format {
+            RenderFormat::Markdown => {
+                rendered.push('\n');
+                for (i, url) in self.source_attributions.iter().enumerate() {
+                    write!(rendered, "\n[^{}]: <{url}>", i + 1).unwrap();
+                }
+            }
+            RenderFormat::PlainText => {}
+            RenderFormat::Html => {
+                rendered.push_str("\n<ol>");
+                for url in &self.source_attributions {
+                    let url = escape_html(url);
+                    write!(rendered, r#"<li><a href="{url}">{url}</a></li>"#).unwrap();
+                }
+                rendered.push_str("</ol>");
+            }
+            RenderFormat::Ansi => {
+                rendered.push('\n');
+                for (i, url) in self.source_attributions.iter().enumerate() {
+                    write!(rendered, "\n[{}]: {}", i + 1, hyperlink(url, url)).unwrap();
+                }
+            }
+        }
+        rendered
+    }
+}
+
+/// Split the text at the footnote markers, dropping an unfinished marker at the end.
+fn segments(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = vec![];
+    let mut rest = text;
+    while let Some(start) = rest.find("[^") {
+        let after = &rest[start + 2..];
+        let digits = after.len() - after.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let closing = &after[digits..];
+        if closing.starts_with("^]") && digits > 0 {
+            segments.push(Segment::Text(&rest[..start]));
+            segments.push(Segment::Footnote(after[..digits].parse().unwrap_or(0)));
+            rest = &closing[2..];
+        } else if closing.len() < 2 && "^]".starts_with(closing) {
+            // cut off while streaming, eg. "[^1" or "[^"
+            rest = &rest[..start];
+            break;
+        } else {
+            segments.push(Segment::Text(&rest[..start + 2]));
+            rest = after;
+        }
+    }
+    // maybe the start of a marker too
+    if rest.ends_with('[') {
+        rest = &rest[..rest.len() - 1];
+    }
+    segments.push(Segment::Text(rest));
+    segments
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// An OSC-8 terminal hyperlink.
+fn hyperlink(url: &str, text: &str) -> String {
+    format!("\x1b]8;;{url}\x1b\\{text}\x1b]8;;\x1b\\")
+}
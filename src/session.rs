@@ -1,26 +1,29 @@
-use crate::{conversation_meta, ConversationMeta, CookieInFile};
+use crate::{
+    context::split_text, conversation_meta, trace::TurnTrace, Action, ChatMetrics, ClientSettings,
+    Connection, ConversationMeta, ConversationMetaCreatingError, CookieInFile, CookieStore,
+    DocumentContext, ImageSource, ImageUploadError, ImageUploader, InvalidProfile, ParseMode,
+    RateLimited, RateLimiter, SettingsError, Transport, WebSocketTransport, WhenTooLong,
+};
 use async_stream::try_stream;
 use base64::{engine::general_purpose, Engine};
-use futures_util::{SinkExt, Stream, StreamExt};
+use futures_util::{Stream, StreamExt};
 use rand::{distributions::Slice, Rng};
 use reqwest::header::{HeaderMap, HeaderValue};
-use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
-use std::pin::Pin;
-use thiserror::Error;
-use tokio::net::TcpStream;
-use tokio_tungstenite::{
-    connect_async,
-    tungstenite::{
-        http::{self},
-        Message,
-    },
-    MaybeTlsStream, WebSocketStream,
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Map, Value};
+use std::{
+    fmt,
+    net::IpAddr,
+    pin::Pin,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
 };
+use thiserror::Error;
+use tokio_tungstenite::tungstenite::http;
 use uuid::Uuid;
 
-const DELIMITER: u8 = 0x1e;
-
 fn random_hex_string(length: usize) -> String {
     let hex_charactors: Vec<char> = "0123456789abcdef".chars().collect();
     rand::thread_rng()
@@ -29,51 +32,25 @@ fn random_hex_string(length: usize) -> String {
         .collect()
 }
 
-fn headers(uuid: &str, forwarded_ip: &str) -> HeaderMap {
+fn headers(
+    host: &str,
+    uuid: &str,
+    forwarded_ip: Option<IpAddr>,
+    settings: &ClientSettings,
+) -> Result<HeaderMap> {
     let mut headers = HeaderMap::new();
     headers.insert("accept", HeaderValue::from_static("application/json"));
     headers.insert(
         "accept-language",
-        HeaderValue::from_static("en-US,en;q=0.9"),
+        HeaderValue::from_str(&settings.locale.accept_language())
+            .unwrap_or(HeaderValue::from_static("en-US,en;q=0.9")),
     );
     headers.insert("content-type", HeaderValue::from_static("application/json"));
-    headers.insert(
-        "sec-ch-ua",
-        HeaderValue::from_static(
-            "\"Not_A Brand\";v=\"99\", \"Microsoft Edge\";v=\"110\", \"Chromium\";v=\"110\"",
-        ),
-    );
-    headers.insert("sec-ch-ua-arch", HeaderValue::from_static("\"x86\""));
-    headers.insert("sec-ch-ua-bitness", HeaderValue::from_static("\"64\""));
-    headers.insert(
-        "sec-ch-ua-full-version",
-        HeaderValue::from_static("\"109.0.1518.78\""),
-    );
-    headers.insert(
-        "sec-ch-ua-full-version-list",
-        HeaderValue::from_static(
-            "\"Chromium\";v=\"110.0.5481.192\", \"Not A(Brand\";v=\"24.0.0.0\", \"Microsoft Edge\";v=\"110.0.1587.69\"",
-        ),
-    );
-    headers.insert("sec-ch-ua-mobile", HeaderValue::from_static("?0"));
-    headers.insert("sec-ch-ua-model", HeaderValue::from_static(""));
-    headers.insert(
-        "sec-ch-ua-platform",
-        HeaderValue::from_static("\"Windows\""),
-    );
-    headers.insert(
-        "sec-ch-ua-platform-version",
-        HeaderValue::from_static("\"15.0.0\""),
-    );
+    settings.profile.apply(&mut headers)?;
+    settings.forwarded_for.validate()?;
     headers.insert("sec-fetch-dest", HeaderValue::from_static("empty"));
     headers.insert("sec-fetch-mode", HeaderValue::from_static("cors"));
     headers.insert("sec-fetch-site", HeaderValue::from_static("same-origin"));
-    headers.insert(
-        "x-ms-useragent",
-        HeaderValue::from_static(
-            "azsdk-js-api-client-factory/1.0.0-beta.1 core-rest-pipeline/1.10.0 OS/Win32",
-        ),
-    );
     headers.insert(
         "Referer",
         HeaderValue::from_static("https://www.bing.com/search?q=Bing+AI&showconv=1&FORM=hpcodx"),
@@ -87,10 +64,12 @@ fn headers(uuid: &str, forwarded_ip: &str) -> HeaderMap {
         "x-ms-client-request-id",
         HeaderValue::from_str(uuid).unwrap(),
     );
-    headers.insert(
-        "x-forwarded-for",
-        HeaderValue::from_str(forwarded_ip).unwrap(),
-    );
+    if let Some(forwarded_ip) = forwarded_ip {
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_str(&forwarded_ip.to_string()).unwrap(),
+        );
+    }
     let websocket_key = random_hex_string(16);
     let websocket_key_base64 = general_purpose::STANDARD.encode(websocket_key);
     headers.insert(
@@ -100,18 +79,8 @@ fn headers(uuid: &str, forwarded_ip: &str) -> HeaderMap {
     headers.insert("Sec-WebSocket-Version", HeaderValue::from_static("13"));
     headers.insert("Connection", HeaderValue::from_static("Upgrade"));
     headers.insert("Upgrade", HeaderValue::from_static("websocket"));
-    headers.insert("Host", HeaderValue::from_static("sydney.bing.com"));
-    headers
-}
-
-fn random_forwarded_ip() -> String {
-    let mut rng = rand::thread_rng();
-    format!(
-        "13.{}.{}.{}",
-        rng.gen_range(104u8..=107u8),
-        rng.gen_range(0u8..=255),
-        rng.gen_range(0u8..=255)
-    )
+    headers.insert("Host", HeaderValue::from_str(host).unwrap());
+    Ok(headers)
 }
 
 /// Conversation Style of bing.
@@ -135,6 +104,46 @@ impl From<ConversationStyle> for &'static str {
     }
 }
 
+impl ConversationStyle {
+    /// The name of the tone bing uses for the style, eg. when uploading an image for a conversation.
+    pub fn tone(self) -> &'static str {
+        match self {
+            ConversationStyle::Creative => "Creative",
+            ConversationStyle::Balanced => "Balanced",
+            ConversationStyle::Precise => "Precise",
+        }
+    }
+}
+
+impl FromStr for ConversationStyle {
+    type Err = ParseConversationStyleError;
+
+    /// Parse "creative", "balanced" or "precise", ignoring case.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "creative" => Ok(ConversationStyle::Creative),
+            "balanced" => Ok(ConversationStyle::Balanced),
+            "precise" => Ok(ConversationStyle::Precise),
+            _ => Err(ParseConversationStyleError(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for ConversationStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ConversationStyle::Creative => "creative",
+            ConversationStyle::Balanced => "balanced",
+            ConversationStyle::Precise => "precise",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("Unknown conversation style {0:?}, should be one of: creative, balanced, precise")]
+pub struct ParseConversationStyleError(String);
+
 /// A session represent a chat with bing.
 /// It implements `Serialize` and `Deserialize`
 /// Thus can be dumped to/load from external storage to pause and continue a chat.
@@ -143,8 +152,44 @@ pub struct ChatSession {
     conversation_meta: ConversationMeta,
     invocation_id: usize,
     uuid: String,
-    ip: String,
+    ip: Option<IpAddr>,
     style: ConversationStyle,
+    #[serde(default)]
+    settings: ClientSettings,
+    #[serde(default)]
+    account: Option<String>,
+    /// for creating new conversations, not dumped with the session.
+    #[serde(skip)]
+    cookies: Option<CookieStore>,
+    /// shared with the streams of the session, which record their turns once answered.
+    #[serde(
+        default,
+        serialize_with = "serialize_transcript",
+        deserialize_with = "deserialize_transcript"
+    )]
+    transcript: Arc<Mutex<Vec<Turn>>>,
+}
+
+fn serialize_transcript<S: Serializer>(
+    transcript: &Arc<Mutex<Vec<Turn>>>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    transcript.lock().unwrap().serialize(serializer)
+}
+
+fn deserialize_transcript<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Arc<Mutex<Vec<Turn>>>, D::Error> {
+    Ok(Arc::new(Mutex::new(Vec::deserialize(deserializer)?)))
+}
+
+/// A prompt of the user and the answer of bing, see [`ChatSession::transcript`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Turn {
+    /// text the user sent, before it was split if it was too long.
+    pub prompt: String,
+    /// the whole answer.
+    pub answer: NewBingResponseMessage,
 }
 
 /// Response provided by bing.
@@ -152,10 +197,244 @@ pub struct ChatSession {
 pub struct NewBingResponseMessage {
     /// text content of the response.
     pub text: String,
-    /// suggested responses of the response.
+    /// suggested responses of the response, their texts,
+    /// see [`suggestions`](NewBingResponseMessage::suggestions) for sending them.
     pub suggested_responses: Vec<String>,
+    /// suggested responses of the response, for [`ChatSession::send_suggestion`].
+    #[serde(default)]
+    pub suggestions: Vec<SuggestedResponse>,
     /// source attributions of the response.
     pub source_attributions: Vec<String>,
+    /// other messages bing sent during the turn, eg. the searches it performed.
+    #[serde(default)]
+    pub parts: Vec<ResponsePart>,
+    /// fields of the answer not parsed above, only kept in [`ParseMode::Lenient`].
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub extra: Map<String, Value>,
+    /// fields missing or of another type, replaced with defaults in [`ParseMode::Lenient`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<ParseWarning>,
+}
+
+/// A field of an answer that could not be parsed, see [`ParseMode::Lenient`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseWarning {
+    /// where the field is, eg. "newbing_response.item".
+    pub object_name: String,
+    /// name of the field.
+    pub field_name: String,
+    /// the type the field should have, `None` if it is missing.
+    pub expected_type: Option<String>,
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{} ", self.object_name, self.field_name)?;
+        match &self.expected_type {
+            None => write!(f, "is missing"),
+            Some(expected_type) => write!(f, "should be of type {expected_type}"),
+        }
+    }
+}
+
+/// A follow-up bing suggests, send it back with [`ChatSession::send_suggestion`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuggestedResponse {
+    /// text of the suggestion.
+    pub text: String,
+    /// id bing gave the suggestion.
+    #[serde(default)]
+    pub message_id: Option<String>,
+    /// type of the suggestion message, usually "Suggestion".
+    #[serde(default)]
+    pub message_type: Option<String>,
+}
+
+impl fmt::Display for SuggestedResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// What happened during a turn, yielded by [`ChatSession::event_stream`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum ChatEvent {
+    /// The answer so far, the last one is the whole answer.
+    Message(NewBingResponseMessage),
+    /// Bing revoked the answer, eg. it was flagged as offensive,
+    /// the text shown so far should be replaced with `text`.
+    Retracted {
+        /// what bing shows instead, usually an apology.
+        text: String,
+    },
+    /// Bing ended the conversation, a new one must be created to continue chatting.
+    Disengaged {
+        /// what bing says when ending the conversation.
+        text: String,
+    },
+    /// The turn ended, after the last [`ChatEvent::Message`], with everything bing sent.
+    Completed(TurnResult),
+}
+
+/// Everything bing sent during a turn, returned by [`ChatSession::send_message_full`]
+/// and yielded as [`ChatEvent::Completed`] at the end of an event stream.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnResult {
+    /// the whole answer, from the final message of the turn.
+    pub message: NewBingResponseMessage,
+    /// the answer so far as it was streamed, before the final message.
+    pub intermediate: Vec<NewBingResponseMessage>,
+    /// how many messages the conversation allows.
+    pub throttling: Option<Throttling>,
+    /// id bing gave the request.
+    pub request_id: Option<String>,
+    /// from connecting to the chat hub to the first text of the answer.
+    pub time_to_first_token: Option<Duration>,
+}
+
+/// How many messages the conversation allows, sent by bing with each answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Throttling {
+    /// messages the user sent in the conversation, including this one.
+    pub user_messages: u64,
+    /// messages the user can send in the conversation.
+    pub max_user_messages: u64,
+}
+
+/// Details of a turn sent along the messages.
+#[derive(Debug, Clone, Default)]
+struct TurnInfo {
+    request_id: Option<String>,
+    throttling: Option<Throttling>,
+}
+
+impl TurnInfo {
+    fn from_response(value: &Value) -> Self {
+        let throttling = &value["item"]["throttling"];
+        Self {
+            request_id: value["arguments"][0]["requestId"]
+                .as_str()
+                .or_else(|| value["item"]["requestId"].as_str())
+                .map(ToString::to_string),
+            throttling: throttling["numUserMessagesInConversation"]
+                .as_u64()
+                .zip(throttling["maxNumUserMessagesInConversation"].as_u64())
+                .map(|(user_messages, max_user_messages)| Throttling {
+                    user_messages,
+                    max_user_messages,
+                }),
+        }
+    }
+}
+
+/// Gathers the messages of a turn into a [`TurnResult`].
+#[derive(Default)]
+struct TurnCollector {
+    intermediate: Vec<NewBingResponseMessage>,
+    request_id: Option<String>,
+}
+
+impl TurnCollector {
+    fn intermediate(&mut self, message: NewBingResponseMessage, info: TurnInfo) {
+        self.request_id = self.request_id.take().or(info.request_id);
+        self.intermediate.push(message);
+    }
+
+    fn finish(
+        self,
+        message: NewBingResponseMessage,
+        info: TurnInfo,
+        trace: &TurnTrace,
+    ) -> TurnResult {
+        TurnResult {
+            message,
+            intermediate: self.intermediate,
+            throttling: info.throttling,
+            request_id: info.request_id.or(self.request_id),
+            time_to_first_token: trace.time_to_first_token(),
+        }
+    }
+}
+
+/// A message other than the answer itself, sent by bing during a turn.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ResponsePart {
+    /// Bing is searching the web, eg. "Searching for: **rust**".
+    SearchQuery {
+        /// text shown to the user.
+        text: String,
+        /// the query itself.
+        query: String,
+    },
+    /// Results of a web search.
+    SearchResults(Vec<SearchResult>),
+    /// Bing requests generating content, eg. images, with the text as the prompt.
+    GenerateContent {
+        /// kind of the content, eg. "IMAGE".
+        content_type: String,
+        /// prompt for generating the content.
+        text: String,
+    },
+    /// Progress message, eg. "Generating answers for you...".
+    Loader(String),
+}
+
+/// A web search result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    /// title of the page.
+    pub title: String,
+    /// url of the page.
+    pub url: String,
+    /// relevant snippets in the page.
+    pub snippets: Vec<String>,
+}
+
+impl ResponsePart {
+    fn from_message(message: &Value) -> Option<Self> {
+        let text = message["text"].as_str().unwrap_or("").to_string();
+        match message["messageType"].as_str()? {
+            "InternalSearchQuery" => Some(Self::SearchQuery {
+                query: message["hiddenText"]
+                    .as_str()
+                    .map(ToString::to_string)
+                    .unwrap_or_else(|| {
+                        text.trim_start_matches("Searching for: ").replace("**", "")
+                    }),
+                text,
+            }),
+            "InternalSearchResult" => {
+                let results = message["groundingInfo"]
+                    .as_object()?
+                    .values()
+                    .filter_map(Value::as_array)
+                    .flatten()
+                    .filter_map(|result| {
+                        Some(SearchResult {
+                            title: result["title"].as_str().unwrap_or("").to_string(),
+                            url: result["url"].as_str()?.to_string(),
+                            snippets: result["snippets"]
+                                .as_array()
+                                .map(Vec::as_slice)
+                                .unwrap_or_default()
+                                .iter()
+                                .filter_map(Value::as_str)
+                                .map(ToString::to_string)
+                                .collect(),
+                        })
+                    })
+                    .collect();
+                Some(Self::SearchResults(results))
+            }
+            "GenerateContentQuery" => Some(Self::GenerateContent {
+                content_type: message["contentType"].as_str().unwrap_or("").to_string(),
+                text,
+            }),
+            "InternalLoaderMessage" => Some(Self::Loader(text)),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -164,7 +443,13 @@ struct NewBingRequestMessage {
     author: &'static str,
     input_method: &'static str,
     text: String,
-    message_type: &'static str,
+    message_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    original_image_url: Option<String>,
 }
 
 impl NewBingRequestMessage {
@@ -173,7 +458,33 @@ impl NewBingRequestMessage {
             author: "user",
             input_method: "Keyboard",
             text,
-            message_type: "Chat",
+            message_type: "Chat".to_string(),
+            message_id: None,
+            image_url: None,
+            original_image_url: None,
+        }
+    }
+
+    /// Attach an image uploaded with [`ImageUploader`].
+    fn with_image(mut self, image_url: String) -> Self {
+        self.original_image_url = Some(image_url.clone());
+        self.image_url = Some(image_url);
+        self
+    }
+
+    /// The message sent when the user clicks a suggested response.
+    fn suggestion(suggestion: &SuggestedResponse) -> Self {
+        Self {
+            author: "user",
+            input_method: "Suggestion",
+            text: suggestion.text.clone(),
+            message_type: suggestion
+                .message_type
+                .clone()
+                .unwrap_or_else(|| "Suggestion".to_string()),
+            message_id: suggestion.message_id.clone(),
+            image_url: None,
+            original_image_url: None,
         }
     }
 }
@@ -195,15 +506,81 @@ struct Argument {
     conversation_signature: String,
     participant: Participant,
     conversation_id: String,
+    market: String,
+    region: String,
+    location_hints: Vec<LocationHint>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    previous_messages: Vec<PreviousMessage>,
+}
+
+/// Context of a message, eg. the web page the user is viewing.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PreviousMessage {
+    author: &'static str,
+    description: String,
+    context_type: &'static str,
+    message_type: &'static str,
+    message_id: String,
+    source_name: String,
+    source_url: String,
+    privacy: &'static str,
+}
+
+impl PreviousMessage {
+    fn from_document(document: &DocumentContext) -> Vec<Self> {
+        document
+            .chunks()
+            .into_iter()
+            .enumerate()
+            .map(|(i, chunk)| Self {
+                author: "user",
+                description: chunk,
+                context_type: "WebPage",
+                message_type: "Context",
+                message_id: format!("discover-web--page-ping-mriduna-----{i}"),
+                source_name: document.title.clone(),
+                source_url: document.url.clone(),
+                privacy: "Internal",
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct LocationHint {
+    country: String,
+    state: String,
+    city: String,
+    #[serde(rename = "timezoneoffset")]
+    timezone_offset: i32,
+    country_confidence: u8,
+    #[serde(rename = "Center")]
+    center: LocationCenter,
+    #[serde(rename = "RegionType")]
+    region_type: u8,
+    #[serde(rename = "SourceType")]
+    source_type: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+struct LocationCenter {
+    latitude: f64,
+    longitude: f64,
 }
 
 impl Argument {
     pub fn new(
         conversation_meta: ConversationMeta,
         style: ConversationStyle,
+        settings: &ClientSettings,
         is_start_of_session: bool,
-        text: &str,
+        message: NewBingRequestMessage,
+        previous_messages: Vec<PreviousMessage>,
     ) -> Self {
+        let locale = &settings.locale;
         Self {
             source: "cib",
             options_sets: [
@@ -221,12 +598,32 @@ impl Argument {
             slice_ids: ["222dtappid", "225cricinfo", "224locals0"],
             trace_id: random_hex_string(32),
             is_start_of_session,
-            message: NewBingRequestMessage::new(text.to_string()),
+            message,
             conversation_signature: conversation_meta.conversation_signature.to_string(),
             participant: Participant {
                 id: conversation_meta.client_id.to_string(),
             },
             conversation_id: conversation_meta.conversation_id,
+            market: locale.market.clone(),
+            region: locale.region.clone(),
+            location_hints: locale
+                .location
+                .iter()
+                .map(|location| LocationHint {
+                    country: location.country.clone(),
+                    state: location.state.clone(),
+                    city: location.city.clone(),
+                    timezone_offset: location.timezone_offset,
+                    country_confidence: 8,
+                    center: LocationCenter {
+                        latitude: location.latitude,
+                        longitude: location.longitude,
+                    },
+                    region_type: 2,
+                    source_type: 1,
+                })
+                .collect(),
+            previous_messages,
         }
     }
 }
@@ -245,15 +642,19 @@ impl NewBingRequest {
     fn new(
         conversation_meta: ConversationMeta,
         style: ConversationStyle,
+        settings: &ClientSettings,
         invocation_id: usize,
-        text: &str,
+        message: NewBingRequestMessage,
+        previous_messages: Vec<PreviousMessage>,
     ) -> Self {
         Self {
             arguments: [Argument::new(
                 conversation_meta,
                 style,
+                settings,
                 invocation_id == 0,
-                text,
+                message,
+                previous_messages,
             )],
             invocation_id: format!("{invocation_id}"),
             target: "chat",
@@ -264,159 +665,317 @@ impl NewBingRequest {
 
 #[derive(Debug, Clone)]
 enum SignalRNewBingResponse {
-    Invocation(NewBingResponseMessage),
-    StreamItem(NewBingResponseMessage),
+    Invocation(NewBingResponseMessage, TurnInfo),
+    StreamItem(NewBingResponseMessage, TurnInfo),
+    Retracted(String),
+    Disengaged(String),
     EndOfResponse,
     Ping,
     Unknown,
 }
 
-impl<'de> serde::Deserialize<'de> for SignalRNewBingResponse {
-    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
-        let value = Value::deserialize(d)?;
+impl SignalRNewBingResponse {
+    fn parse(record: &str, mode: ParseMode) -> Result<Self> {
+        let value: Value = serde_json::from_str(record)?;
 
-        Ok(match value.get("type").and_then(Value::as_u64).unwrap() {
-            1 => SignalRNewBingResponse::Invocation(deserialize_invocation(value).unwrap()),
-            2 => SignalRNewBingResponse::StreamItem(deserialize_newbing_response(value).unwrap()),
-            3 => SignalRNewBingResponse::EndOfResponse,
-            6 => SignalRNewBingResponse::Ping,
-            _ => SignalRNewBingResponse::Unknown,
-        })
+        let messages = value["arguments"][0]["messages"]
+            .as_array()
+            .or_else(|| value["item"]["messages"].as_array());
+        for message in messages.map(Vec::as_slice).unwrap_or_default() {
+            let text = message["text"].as_str().unwrap_or_default().to_string();
+            if message["messageType"] == "Disengaged" {
+                return Ok(SignalRNewBingResponse::Disengaged(text));
+            }
+            // only the answer itself, internal messages carry `hiddenText` too
+            let is_answer = message["author"] == "bot" && message.get("messageType").is_none();
+            if is_answer && is_retracted(message) {
+                return Ok(SignalRNewBingResponse::Retracted(text));
+            }
+        }
+
+        // the handshake response has no type
+        Ok(
+            match value
+                .get("type")
+                .and_then(Value::as_u64)
+                .unwrap_or_default()
+            {
+                1 => SignalRNewBingResponse::Invocation(
+                    deserialize_invocation(&value, mode),
+                    TurnInfo::from_response(&value),
+                ),
+                2 => SignalRNewBingResponse::StreamItem(
+                    deserialize_newbing_response(&value, mode)?,
+                    TurnInfo::from_response(&value),
+                ),
+                3 => SignalRNewBingResponse::EndOfResponse,
+                6 => SignalRNewBingResponse::Ping,
+                _ => SignalRNewBingResponse::Unknown,
+            },
+        )
     }
 }
 
-fn deserialize_invocation(value: Value) -> Result<NewBingResponseMessage> {
-    let content = value["arguments"][0]["messages"][0]["text"]
-        .as_str()
-        .unwrap_or("")
-        .to_string();
-    let res = NewBingResponseMessage {
-        text: content,
+/// Whether bing revoked the message: flagged as offensive, its text hidden,
+/// or replaced with a canned apology.
+fn is_retracted(message: &Value) -> bool {
+    matches!(
+        message["offense"].as_str(),
+        Some("OffenseTrigger" | "Offensive")
+    ) || message.get("hiddenText").is_some()
+        || message["contentOrigin"] == "Apology"
+}
+
+/// Fields of the answer message parsed into [`NewBingResponseMessage`].
+const PARSED_FIELDS: [&str; 5] = [
+    "text",
+    "author",
+    "messageType",
+    "suggestedResponses",
+    "sourceAttributions",
+];
+
+/// Fields of the answer not parsed, kept in [`ParseMode::Lenient`].
+fn extra_fields(answer: &Value, mode: ParseMode) -> Map<String, Value> {
+    match (answer.as_object(), mode) {
+        (Some(answer), ParseMode::Lenient) => answer
+            .iter()
+            .filter(|(key, _)| !PARSED_FIELDS.contains(&key.as_str()))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect(),
+        _ => Map::new(),
+    }
+}
+
+/// Looks up the fields of a response, failing on missing or mistyped ones when strict,
+/// falling back to a default and recording a warning when lenient.
+struct FieldReader {
+    mode: ParseMode,
+    warnings: Vec<ParseWarning>,
+}
+
+impl FieldReader {
+    fn new(mode: ParseMode) -> Self {
+        Self {
+            mode,
+            warnings: vec![],
+        }
+    }
+
+    fn missing(&mut self, object_name: &'static str, field_name: &'static str) -> Result<()> {
+        match self.mode {
+            ParseMode::Strict => Err(ChatError::GetFieldError {
+                object_name,
+                field_name,
+            }),
+            ParseMode::Lenient => {
+                self.warnings.push(ParseWarning {
+                    object_name: object_name.to_string(),
+                    field_name: field_name.to_string(),
+                    expected_type: None,
+                });
+                Ok(())
+            }
+        }
+    }
+
+    fn mistyped(
+        &mut self,
+        object_name: &'static str,
+        field_name: &'static str,
+        expected_type: &'static str,
+    ) -> Result<()> {
+        match self.mode {
+            ParseMode::Strict => Err(ChatError::FieldTypeError {
+                object_name,
+                field_name,
+                expected_type,
+            }),
+            ParseMode::Lenient => {
+                self.warnings.push(ParseWarning {
+                    object_name: object_name.to_string(),
+                    field_name: field_name.to_string(),
+                    expected_type: Some(expected_type.to_string()),
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// The field cast to the expected type, `None` if it is missing or of another type.
+    fn get<'v, T>(
+        &mut self,
+        value: &'v Value,
+        object_name: &'static str,
+        field_name: &'static str,
+        expected_type: &'static str,
+        cast: impl FnOnce(&'v Value) -> Option<T>,
+    ) -> Result<Option<T>> {
+        let Some(field) = value.get(field_name) else {
+            self.missing(object_name, field_name)?;
+            return Ok(None);
+        };
+        let cast = cast(field);
+        if cast.is_none() {
+            self.mistyped(object_name, field_name, expected_type)?;
+        }
+        Ok(cast)
+    }
+
+    fn str<'v>(
+        &mut self,
+        value: &'v Value,
+        object_name: &'static str,
+        field_name: &'static str,
+    ) -> Result<Option<&'v str>> {
+        self.get(value, object_name, field_name, "str", Value::as_str)
+    }
+
+    fn array<'v>(
+        &mut self,
+        value: &'v Value,
+        object_name: &'static str,
+        field_name: &'static str,
+    ) -> Result<&'v [Value]> {
+        Ok(self
+            .get(value, object_name, field_name, "array", Value::as_array)?
+            .map(Vec::as_slice)
+            .unwrap_or_default())
+    }
+}
+
+/// The partial answer of an update, without suggestions nor sources.
+/// Fields are often missing until the answer ends, so nothing is warned about
+/// in either mode, `mode` only decides whether unknown fields are kept.
+fn deserialize_invocation(value: &Value, mode: ParseMode) -> NewBingResponseMessage {
+    let messages = value["arguments"][0]["messages"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let answer = messages.iter().find(|msg| msg.get("messageType").is_none());
+    NewBingResponseMessage {
+        text: answer
+            .and_then(|msg| msg["text"].as_str())
+            .unwrap_or("")
+            .to_string(),
         suggested_responses: vec![],
+        suggestions: vec![],
         source_attributions: vec![],
+        parts: messages
+            .iter()
+            .filter_map(ResponsePart::from_message)
+            .collect(),
+        extra: answer
+            .map(|answer| extra_fields(answer, mode))
+            .unwrap_or_default(),
+        warnings: vec![],
+    }
+}
+
+fn deserialize_newbing_response(value: &Value, mode: ParseMode) -> Result<NewBingResponseMessage> {
+    const ANSWER: &str = "newbing_response.item.messages[{author == bot, messageType != null}]";
+    let mut reader = FieldReader::new(mode);
+    let messages = match reader.get(
+        value,
+        "newbing_response",
+        "item",
+        "object",
+        Value::as_object,
+    )? {
+        Some(_) => reader.array(&value["item"], "newbing_response.item", "messages")?,
+        None => &[],
     };
-    Ok(res)
-}
-
-fn deserialize_newbing_response(value: Value) -> Result<NewBingResponseMessage> {
-    let content = &value
-        .get("item")
-        .ok_or(ChatError::GetFieldError {
-            object_name: "newbing_response",
-            field_name: "item",
-        })?
-        .get("messages")
-        .ok_or(ChatError::GetFieldError {
-            object_name: "newbing_response.item",
-            field_name: "messages",
-        })?
-        .as_array()
-        .ok_or(ChatError::FieldTypeError {
-            object_name: "newbing_response.item",
-            field_name: "messages",
-            expected_type: "str",
-        })?
+    let parts = messages
         .iter()
-        .find(|msg| {
-            msg.get("messageType").is_none()
-                && msg
-                    .get("author")
-                    .and_then(|author| author.as_str())
-                    .map(|it| it == "bot")
-                    .unwrap_or(false)
-        })
-        .ok_or(ChatError::FieldTypeError {
-            object_name: "newbing_response.item",
-            field_name: "messages[{author == bot, messageType != null}]",
-            expected_type: "str",
-        })?;
-    let text = content
-        .get("text")
-        .ok_or(ChatError::GetFieldError {
-            object_name: "newbing_response.item.messages[{author == bot, messageType != null}]",
-            field_name: "text",
-        })?
-        .as_str()
-        .ok_or(ChatError::FieldTypeError {
-            object_name: "newbing_response.item.messages[{author == bot, messageType != null}]",
-            field_name: "text",
-            expected_type: "str",
-        })?
+        .filter_map(ResponsePart::from_message)
+        .collect();
+    let Some(content) = messages.iter().find(|msg| {
+        msg.get("messageType").is_none()
+            && msg
+                .get("author")
+                .and_then(|author| author.as_str())
+                .map(|it| it == "bot")
+                .unwrap_or(false)
+    }) else {
+        reader.missing(
+            "newbing_response.item",
+            "messages[{author == bot, messageType != null}]",
+        )?;
+        return Ok(NewBingResponseMessage {
+            text: String::new(),
+            suggested_responses: vec![],
+            suggestions: vec![],
+            source_attributions: vec![],
+            parts,
+            extra: Map::new(),
+            warnings: reader.warnings,
+        });
+    };
+    let text = reader
+        .str(content, ANSWER, "text")?
+        .unwrap_or("")
         .to_string();
-    let suggested_responses = content
-        .get("suggestedResponses")
-        .ok_or(ChatError::GetFieldError {
-            object_name: "newbing_response.item.messages[{author == bot, messageType != null}]",
-            field_name: "suggestedResponses",
-        })?
-        .as_array()
-        .ok_or(ChatError::FieldTypeError {
-            object_name: "newbing_response.item.messages[{author == bot, messageType != null}]",
-            field_name: "suggestedResponses",
-            expected_type: "array",
-        })?
-        .iter()
-        .map(|suggested_response|{
-            Ok(suggested_response
-                .get("text")
-                .ok_or(ChatError::GetFieldError {
-                    object_name: "newbing_response.item.messages[{author == bot, messageType != null}].suggestedResponses",
-                    field_name: "text",
-                })?
-                .as_str()
-                .ok_or(ChatError::FieldTypeError {
-                    object_name: "newbing_response.item.messages[{author == bot, messageType != null}].suggestedResponses",
-                    field_name: "text",
-                    expected_type: "str",
-                })?
-                .to_string())
-        })
-        .collect::<Result<_>>()?;
-    let source_attributions = content
-        .get("sourceAttributions")
-        .ok_or(ChatError::GetFieldError {
-            object_name: "newbing_response.item.messages[{author == bot, messageType != null}]",
-            field_name: "sourceAttributions",
-        })?
-        .as_array()
-        .ok_or(ChatError::FieldTypeError {
-            object_name: "newbing_response.item.messages[{author == bot, messageType != null}]",
-            field_name: "sourceAttributions",
-            expected_type: "array",
-        })?
-        .iter()
-        .map(|it| {
-            Ok(it
-                .get("seeMoreUrl")
-                .ok_or(ChatError::GetFieldError {
-                    object_name:
-                        "newbing_response.item.messages[{author == bot, messageType != null}].sourceAttributions",
-                    field_name: "seeMoreUrl",
-                })?
-                .as_str()
-                .ok_or(ChatError::FieldTypeError {
-                    object_name:
-                        "newbing_response.item.messages[{author == bot, messageType != null}].sourceAttributions",
-                    field_name: "seeMoreUrl",
-                    expected_type: "str",
-                })?
-                .to_string())
-        })
-        .collect::<Result<_>>()?;
+    let mut suggestions = vec![];
+    for suggested_response in reader.array(content, ANSWER, "suggestedResponses")? {
+        const SUGGESTION: &str =
+            "newbing_response.item.messages[{author == bot, messageType != null}].suggestedResponses";
+        if let Some(text) = reader.str(suggested_response, SUGGESTION, "text")? {
+            suggestions.push(SuggestedResponse {
+                text: text.to_string(),
+                message_id: suggested_response["messageId"]
+                    .as_str()
+                    .map(ToString::to_string),
+                message_type: suggested_response["messageType"]
+                    .as_str()
+                    .map(ToString::to_string),
+            });
+        }
+    }
+    let mut source_attributions = vec![];
+    for source in reader.array(content, ANSWER, "sourceAttributions")? {
+        const SOURCE: &str =
+            "newbing_response.item.messages[{author == bot, messageType != null}].sourceAttributions";
+        if let Some(url) = reader.str(source, SOURCE, "seeMoreUrl")? {
+            source_attributions.push(url.to_string());
+        }
+    }
     Ok(NewBingResponseMessage {
         text,
-        suggested_responses,
+        suggested_responses: suggestions.iter().map(|it| it.text.clone()).collect(),
+        suggestions,
         source_attributions,
+        parts,
+        extra: extra_fields(content, mode),
+        warnings: reader.warnings,
     })
 }
 
 impl ChatSession {
+    /// `ip` is sent as `x-forwarded-for` if it is an IP address,
+    /// otherwise, eg. for an empty string, no header is sent, see [`new_with_ip`](ChatSession::new_with_ip).
     pub fn new(
         conversation_meta: ConversationMeta,
         style: ConversationStyle,
         invocation_id: usize,
         uuid: String,
         ip: String,
+    ) -> Self {
+        Self::new_with_ip(
+            conversation_meta,
+            style,
+            invocation_id,
+            uuid,
+            ip.parse().ok(),
+        )
+    }
+
+    /// Like [`new`](ChatSession::new), `None` sends no `x-forwarded-for` header.
+    pub fn new_with_ip(
+        conversation_meta: ConversationMeta,
+        style: ConversationStyle,
+        invocation_id: usize,
+        uuid: String,
+        ip: Option<IpAddr>,
     ) -> Self {
         Self {
             style,
@@ -424,6 +983,10 @@ impl ChatSession {
             invocation_id,
             uuid,
             ip,
+            settings: ClientSettings::default(),
+            account: None,
+            cookies: None,
+            transcript: Arc::default(),
         }
     }
 
@@ -432,120 +995,521 @@ impl ChatSession {
         style: ConversationStyle,
         cookies: &[CookieInFile],
     ) -> conversation_meta::Result<Self> {
+        Self::create_with_settings(style, cookies, ClientSettings::default()).await
+    }
+
+    /// Create a new [`ChatSession`] from cookies, with the client settings.
+    pub async fn create_with_settings(
+        style: ConversationStyle,
+        cookies: &[CookieInFile],
+        settings: ClientSettings,
+    ) -> conversation_meta::Result<Self> {
+        Self::create_in_store(style, &CookieStore::new(cookies), settings, None).await
+    }
+
+    /// Create a new [`ChatSession`] from the cookies of the store, with the client settings,
+    /// the cookies bing sets are kept in the store.
+    pub async fn create_with_store(
+        style: ConversationStyle,
+        store: &CookieStore,
+        settings: ClientSettings,
+    ) -> conversation_meta::Result<Self> {
+        Self::create_in_store(style, store, settings, None).await
+    }
+
+    /// Create a new [`ChatSession`] from the cookies of the store of `account`.
+    pub(crate) async fn create_in_store(
+        style: ConversationStyle,
+        store: &CookieStore,
+        settings: ClientSettings,
+        account: Option<String>,
+    ) -> conversation_meta::Result<Self> {
+        let conversation_meta =
+            ConversationMeta::create_in_store(store, &settings, account.as_deref()).await?;
+        let mut session = Self::from_meta_with_settings(conversation_meta, style, 0, settings);
+        session.account = account;
+        session.cookies = Some(store.clone());
+        Ok(session)
+    }
+
+    /// Create a [`ChatSession`] for an existing conversation,
+    /// `invocation_id` is the count of messages already sent in it.
+    pub fn from_meta(
+        conversation_meta: ConversationMeta,
+        style: ConversationStyle,
+        invocation_id: usize,
+    ) -> Self {
+        Self::from_meta_with_settings(
+            conversation_meta,
+            style,
+            invocation_id,
+            ClientSettings::default(),
+        )
+    }
+
+    /// Create a [`ChatSession`] for an existing conversation, with the client settings,
+    /// eg. to continue it in the same locale.
+    pub fn from_meta_with_settings(
+        conversation_meta: ConversationMeta,
+        style: ConversationStyle,
+        invocation_id: usize,
+        settings: ClientSettings,
+    ) -> Self {
         let uuid = Uuid::new_v4().hyphenated();
         let uuid = uuid.encode_lower(&mut Uuid::encode_buffer()).to_string();
-        Ok(Self {
-            conversation_meta: ConversationMeta::create(cookies).await?,
-            invocation_id: 0,
+        Self {
+            conversation_meta,
+            invocation_id,
             uuid,
-            ip: random_forwarded_ip(),
+            ip: settings.forwarded_for.pick(),
             style,
+            settings,
+            account: None,
+            cookies: None,
+            transcript: Arc::default(),
+        }
+    }
+
+    /// Conversation style of the following messages.
+    pub fn style(&self) -> ConversationStyle {
+        self.style
+    }
+
+    /// Change the conversation style of the following messages.
+    pub fn set_style(&mut self, style: ConversationStyle) {
+        self.style = style;
+    }
+
+    /// The conversation the messages are sent to.
+    pub fn conversation_meta(&self) -> &ConversationMeta {
+        &self.conversation_meta
+    }
+
+    /// Set the cookies [`reset`](ChatSession::reset) creates new conversations with,
+    /// needed for sessions not created from cookies, eg. loaded from a dump.
+    pub fn set_cookies(&mut self, cookies: &[CookieInFile]) {
+        self.cookies = Some(CookieStore::new(cookies));
+    }
+
+    /// Like [`set_cookies`](ChatSession::set_cookies), sharing the cookies of the store.
+    pub fn set_cookie_store(&mut self, store: CookieStore) {
+        self.cookies = Some(store);
+    }
+
+    /// The cookies [`reset`](ChatSession::reset) creates new conversations with,
+    /// including the ones bing set since, `None` unless the session was created from cookies
+    /// or they were [set](ChatSession::set_cookies).
+    pub fn cookie_store(&self) -> Option<&CookieStore> {
+        self.cookies.as_ref()
+    }
+
+    /// Start a new conversation, like the "New topic" button of bing,
+    /// with the cookies, style and settings of this session.
+    ///
+    /// Return the previous session, eg. to archive it with its [`transcript`](ChatSession::transcript),
+    /// the transcript of this session starts empty.
+    /// Fails with [`NoCookies`](crate::ConversationMetaCreatingError::NoCookies) for sessions
+    /// not created from cookies, eg. with [`from_meta`](ChatSession::from_meta) or loaded from a dump.
+    pub async fn reset(&mut self) -> conversation_meta::Result<ChatSession> {
+        let cookies = self
+            .cookies
+            .as_ref()
+            .ok_or(ConversationMetaCreatingError::NoCookies)?;
+        let conversation_meta =
+            ConversationMeta::create_in_store(cookies, &self.settings, self.account.as_deref())
+                .await?;
+        Ok(ChatSession {
+            conversation_meta: std::mem::replace(&mut self.conversation_meta, conversation_meta),
+            invocation_id: std::mem::take(&mut self.invocation_id),
+            uuid: self.uuid.clone(),
+            ip: self.ip,
+            style: self.style,
+            settings: self.settings.clone(),
+            account: self.account.clone(),
+            cookies: self.cookies.clone(),
+            transcript: std::mem::take(&mut self.transcript),
         })
     }
 
-    /// Create a new [`ChatStream`] for chatting with the bot in a [`Stream`].
-    pub async fn chat_stream(&mut self, text: &str) -> Result<ChatStream> {
-        let mut request = http::Request::builder()
-            .uri("wss://sydney.bing.com/sydney/ChatHub")
-            .body(())
-            .unwrap();
-        *(request.headers_mut()) = headers(&self.uuid, &self.ip);
-        let (mut ws_stream, _) = connect_async(request)
-            .await
-            .map_err(|_| ChatError::Network)?;
-        let mut handshake_message =
-            serde_json::to_vec(&json!({"protocol": "json", "version": 1})).unwrap();
-        handshake_message.push(DELIMITER);
-        let message = Message::Binary(handshake_message);
-        ws_stream
-            .send(message)
-            .await
-            .map_err(|_| ChatError::Network)?;
-        let _response = ws_stream
-            .next()
-            .await
-            .unwrap()
-            .map_err(|_| ChatError::Network)?;
+    /// The turns answered in this conversation, including the ones of finished streams,
+    /// dumped with the session.
+    pub fn transcript(&self) -> Vec<Turn> {
+        self.transcript.lock().unwrap().clone()
+    }
 
-        let mut alive_message = serde_json::to_vec(&json!({"type": 6})).unwrap();
-        alive_message.push(DELIMITER);
-        let message = Message::Binary(alive_message);
-        ws_stream.send(message).await.unwrap();
+    /// Client settings used by this session.
+    pub fn settings(&self) -> &ClientSettings {
+        &self.settings
+    }
+
+    /// Name of the [`Account`](crate::Account) in an [`AccountPool`](crate::AccountPool)
+    /// this session was created with.
+    pub fn account(&self) -> Option<&str> {
+        self.account.as_deref()
+    }
+
+    /// Record the account this session was created with.
+    pub fn set_account(&mut self, account: Option<String>) {
+        self.account = account;
+    }
+
+    /// Limit the messages sent in this session, the limiter is not dumped with the session.
+    pub fn set_rate_limiter(&mut self, rate_limiter: Option<Arc<RateLimiter>>) {
+        self.settings.rate_limiter = rate_limiter;
+    }
+
+    /// Change the client settings used by the following messages,
+    /// keeping the `x-forwarded-for` address, see [`set_settings`](ChatSession::set_settings).
+    pub fn settings_mut(&mut self) -> &mut ClientSettings {
+        &mut self.settings
+    }
+
+    /// Change the client settings used by the following messages,
+    /// the `x-forwarded-for` address is picked again.
+    pub fn set_settings(&mut self, settings: ClientSettings) {
+        self.ip = settings.forwarded_for.pick();
+        self.settings = settings;
+    }
+
+    /// Connect to the chat hub and send the message,
+    /// return the connection for reading the responses.
+    async fn connect(
+        &mut self,
+        request_message: NewBingRequestMessage,
+        previous_messages: Vec<PreviousMessage>,
+        trace: &TurnTrace,
+    ) -> Result<Box<dyn Connection>> {
+        if let Some(rate_limiter) = &self.settings.rate_limiter {
+            rate_limiter
+                .acquire(Action::SendMessage, self.account.as_deref())
+                .await?;
+        }
+        let url = &self.settings.endpoints.chat_hub;
+        let host = url
+            .parse::<http::Uri>()
+            .ok()
+            .and_then(|it| it.authority().map(|it| it.to_string()))
+            .ok_or(ChatError::Network)?;
+        let headers = headers(&host, &self.uuid, self.ip, &self.settings)?;
+        let mut connection = match (&self.settings.transport, &self.settings.proxy) {
+            (Some(transport), _) => transport.connect(url, headers).await?,
+            (None, Some(proxy)) => WebSocketTransport::connect_through(proxy, url, headers).await?,
+            (None, None) => WebSocketTransport.connect(url, headers).await?,
+        };
+        trace.connected();
+        send_record(
+            connection.as_mut(),
+            &json!({"protocol": "json", "version": 1}),
+            trace,
+        )
+        .await?;
+        let response = connection.receive().await.ok_or(ChatError::NoResponse)??;
+        response.iter().for_each(|it| trace.received(it));
+        trace.handshake();
+
+        send_record(connection.as_mut(), &json!({"type": 6}), trace).await?;
 
         let msg = NewBingRequest::new(
             self.conversation_meta.clone(),
             self.style,
+            &self.settings,
             self.invocation_id,
-            text,
+            request_message,
+            previous_messages,
         );
-        let mut question_message = serde_json::to_vec(&msg).unwrap();
-        question_message.push(DELIMITER);
-        let message = Message::Binary(question_message);
-        ws_stream.send(message).await.unwrap();
+        send_record(connection.as_mut(), &msg, trace).await?;
         self.invocation_id += 1;
-        let stream = chat_stream(ws_stream);
-        Ok(Box::pin(stream))
+        Ok(connection)
+    }
+
+    /// Create a new [`ChatStream`] for chatting with the bot in a [`Stream`].
+    pub async fn chat_stream(&mut self, text: &str) -> Result<ChatStream> {
+        self.stream(NewBingRequestMessage::new(text.to_string()), vec![])
+            .await
+    }
+
+    /// Create a new [`ChatEventStream`], telling apart retracted answers and ended conversations
+    /// which [`ChatStream`] reports as [`ChatError::Blocked`] and [`ChatError::ConversationEnded`].
+    pub async fn event_stream(&mut self, text: &str) -> Result<ChatEventStream> {
+        let events = self
+            .events(NewBingRequestMessage::new(text.to_string()), vec![])
+            .await;
+        let events = self.record_error(events)?;
+        Ok(Box::pin(record_errors(
+            events,
+            self.settings.metrics.clone(),
+        )))
+    }
+
+    /// Create a new [`ChatStream`] for asking about a document, eg. a web page.
+    pub async fn chat_stream_with_context(
+        &mut self,
+        text: &str,
+        document: &DocumentContext,
+    ) -> Result<ChatStream> {
+        self.stream(
+            NewBingRequestMessage::new(text.to_string()),
+            PreviousMessage::from_document(document),
+        )
+        .await
+    }
+
+    /// Upload the image with the uploader, and create a [`ChatStream`] for asking about it.
+    pub async fn chat_stream_with_image(
+        &mut self,
+        text: &str,
+        uploader: &ImageUploader,
+        image: ImageSource,
+    ) -> Result<ChatStream> {
+        let message = self.image_message(text, uploader, image).await?;
+        self.stream(message, vec![]).await
+    }
+
+    /// Send a suggested response as if the user clicked it, and return a [`ChatStream`].
+    pub async fn suggestion_stream(
+        &mut self,
+        suggestion: &SuggestedResponse,
+    ) -> Result<ChatStream> {
+        self.stream(NewBingRequestMessage::suggestion(suggestion), vec![])
+            .await
+    }
+
+    async fn stream(
+        &mut self,
+        message: NewBingRequestMessage,
+        previous_messages: Vec<PreviousMessage>,
+    ) -> Result<ChatStream> {
+        let events = self.events(message, previous_messages).await;
+        let events = self.record_error(events)?;
+        let result = Arc::default();
+        Ok(ChatStream {
+            messages: Box::pin(record_errors(
+                chat_stream(events, Arc::clone(&result)),
+                self.settings.metrics.clone(),
+            )),
+            result,
+        })
+    }
+
+    async fn events(
+        &mut self,
+        message: NewBingRequestMessage,
+        previous_messages: Vec<PreviousMessage>,
+    ) -> Result<impl Stream<Item = Result<ChatEvent>>> {
+        let prompt = message.text.clone();
+        let message = self.fit_prompt(message).await?;
+        let trace = self.turn_trace();
+        let connection = self.connect(message, previous_messages, &trace).await?;
+        let mode = self.settings.parse_mode.unwrap_or(ParseMode::Lenient);
+        let transcript = Arc::clone(&self.transcript);
+        Ok(event_stream(connection, trace, mode).inspect(move |event| {
+            if let Ok(ChatEvent::Completed(turn)) = event {
+                transcript.lock().unwrap().push(Turn {
+                    prompt: prompt.clone(),
+                    answer: turn.message.clone(),
+                });
+            }
+        }))
     }
 
     /// Send a message to the session, and return the response.
     pub async fn send_message(&mut self, text: &str) -> Result<NewBingResponseMessage> {
-        let mut request = http::Request::builder()
-            .uri("wss://sydney.bing.com/sydney/ChatHub")
-            .body(())
-            .unwrap();
-        *(request.headers_mut()) = headers(&self.uuid, &self.ip);
-        let (ws_stream, _) = connect_async(request)
+        Ok(self.send_message_full(text).await?.message)
+    }
+
+    /// Send a message to the session, and return the response
+    /// along with the intermediate messages and the details of the turn.
+    pub async fn send_message_full(&mut self, text: &str) -> Result<TurnResult> {
+        self.send(NewBingRequestMessage::new(text.to_string()), vec![])
             .await
-            .map_err(|_| ChatError::Network)?;
-        let (mut write, mut read) = ws_stream.split();
-        let mut handshake_message =
-            serde_json::to_vec(&json!({"protocol": "json", "version": 1})).unwrap();
-        handshake_message.push(DELIMITER);
-        let message = Message::Binary(handshake_message);
-        write.send(message).await.map_err(|_| ChatError::Network)?;
+    }
 
-        let _response = read.next().await.unwrap().map_err(|_| ChatError::Network)?;
+    /// Send a message asking about a document, eg. a web page, and return the response.
+    pub async fn send_message_with_context(
+        &mut self,
+        text: &str,
+        document: &DocumentContext,
+    ) -> Result<NewBingResponseMessage> {
+        let result = self
+            .send(
+                NewBingRequestMessage::new(text.to_string()),
+                PreviousMessage::from_document(document),
+            )
+            .await?;
+        Ok(result.message)
+    }
 
-        let mut alive_message = serde_json::to_vec(&json!({"type": 6})).unwrap();
-        alive_message.push(DELIMITER);
-        let message = Message::Binary(alive_message);
-        write.send(message).await.unwrap();
+    /// Upload the image with the uploader, and send a message asking about it.
+    pub async fn send_message_with_image(
+        &mut self,
+        text: &str,
+        uploader: &ImageUploader,
+        image: ImageSource,
+    ) -> Result<NewBingResponseMessage> {
+        let message = self.image_message(text, uploader, image).await?;
+        Ok(self.send(message, vec![]).await?.message)
+    }
 
-        let msg = NewBingRequest::new(
-            self.conversation_meta.clone(),
-            self.style,
+    async fn image_message(
+        &self,
+        text: &str,
+        uploader: &ImageUploader,
+        image: ImageSource,
+    ) -> Result<NewBingRequestMessage> {
+        // don't upload an image for a prompt which would be rejected
+        self.record_error(self.check_prompt(text))?;
+        let image_url = uploader
+            .upload_for(image, Some((&self.conversation_meta, self.style)))
+            .await
+            .map_err(ChatError::from);
+        let image_url = self.record_error(image_url)?;
+        Ok(NewBingRequestMessage::new(text.to_string()).with_image(image_url))
+    }
+
+    /// Send a suggested response as if the user clicked it, and return the response.
+    pub async fn send_suggestion(
+        &mut self,
+        suggestion: &SuggestedResponse,
+    ) -> Result<NewBingResponseMessage> {
+        let result = self
+            .send(NewBingRequestMessage::suggestion(suggestion), vec![])
+            .await?;
+        Ok(result.message)
+    }
+
+    async fn send(
+        &mut self,
+        message: NewBingRequestMessage,
+        previous_messages: Vec<PreviousMessage>,
+    ) -> Result<TurnResult> {
+        let prompt = message.text.clone();
+        let result = match self.fit_prompt(message).await {
+            Ok(message) => self.send_turn(message, previous_messages).await,
+            Err(e) => Err(e),
+        };
+        if let Ok(turn) = &result {
+            self.transcript.lock().unwrap().push(Turn {
+                prompt,
+                answer: turn.message.clone(),
+            });
+        }
+        self.record_error(result)
+    }
+
+    /// Report the error of the result to the [`ChatMetrics`] of the settings.
+    fn record_error<T>(&self, result: Result<T>) -> Result<T> {
+        if let (Err(e), Some(metrics)) = (&result, &self.settings.metrics) {
+            metrics.record_error(e);
+        }
+        result
+    }
+
+    /// Fail if the prompt is longer than [`PromptLimit`](crate::PromptLimit) allows
+    /// and [`WhenTooLong::Reject`] is set.
+    fn check_prompt(&self, text: &str) -> Result<()> {
+        let limit = self.settings.prompt_limit;
+        let length = text.chars().count();
+        match limit.when_exceeded {
+            WhenTooLong::Reject if length > limit.max_chars => Err(ChatError::PromptTooLong {
+                length,
+                max_chars: limit.max_chars,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the prompt against [`PromptLimit`](crate::PromptLimit), when splitting is enabled
+    /// send all but the last part of a long prompt, and return the message with the last part.
+    async fn fit_prompt(
+        &mut self,
+        message: NewBingRequestMessage,
+    ) -> Result<NewBingRequestMessage> {
+        self.check_prompt(&message.text)?;
+        let limit = self.settings.prompt_limit;
+        let length = message.text.chars().count();
+        if length <= limit.max_chars || !matches!(limit.when_exceeded, WhenTooLong::Split) {
+            return Ok(message);
+        }
+        let too_long = ChatError::PromptTooLong {
+            length,
+            max_chars: limit.max_chars,
+        };
+        // leave room for the longest preamble, there are fewer parts than characters
+        let widest = 10usize.pow(length.to_string().len() as u32) - 1;
+        let preamble_chars = part_preamble(widest - 1, widest).chars().count();
+        let Some(part_chars) = limit
+            .max_chars
+            .checked_sub(preamble_chars)
+            .filter(|&it| it > 0)
+        else {
+            return Err(too_long);
+        };
+        let parts = split_text(&message.text, part_chars);
+        let count = parts.len();
+        let mut parts = parts.into_iter().enumerate();
+        let last = parts.next_back();
+        for (i, part) in parts {
+            let text = format!("{}{part}", part_preamble(i + 1, count));
+            self.send_turn(NewBingRequestMessage::new(text), vec![])
+                .await?;
+        }
+        let (i, part) = last.ok_or(too_long)?;
+        Ok(NewBingRequestMessage {
+            text: format!("{}{part}", part_preamble(i + 1, count)),
+            ..message
+        })
+    }
+
+    fn turn_trace(&self) -> TurnTrace {
+        TurnTrace::new(
+            &self.conversation_meta,
             self.invocation_id,
-            text,
-        );
-        let mut question_message = serde_json::to_vec(&msg).unwrap();
-        question_message.push(DELIMITER);
-        let message = Message::Binary(question_message);
-        write.send(message).await.unwrap();
-        self.invocation_id += 1;
+            self.style,
+            self.settings.metrics.clone(),
+            self.settings.frame_observer.clone(),
+        )
+    }
 
-        while let Some(Ok(response)) = read.next().await {
-            if let Message::Text(content) = response {
-                let signal_r_packages = content
-                    .split('\u{1e}')
-                    .map(|it| it.trim())
-                    .filter(|it| !it.is_empty())
-                    .collect::<Vec<_>>();
-                for signal_r_package in signal_r_packages {
-                    let response: SignalRNewBingResponse = serde_json::from_str(signal_r_package)?;
-                    match response {
-                        SignalRNewBingResponse::StreamItem(message) => return Ok(message),
-                        SignalRNewBingResponse::EndOfResponse => {
-                            break;
-                        }
-                        SignalRNewBingResponse::Ping => {
-                            let mut alive_message =
-                                serde_json::to_vec(&json!({"type": 6})).unwrap();
-                            alive_message.push(DELIMITER);
-                            let message = Message::Binary(alive_message);
-                            write.send(message).await.map_err(|_| ChatError::Network)?;
-                        }
-                        _ => {}
+    async fn send_turn(
+        &mut self,
+        message: NewBingRequestMessage,
+        previous_messages: Vec<PreviousMessage>,
+    ) -> Result<TurnResult> {
+        let trace = self.turn_trace();
+        let mut connection = self.connect(message, previous_messages, &trace).await?;
+        let mode = self.settings.parse_mode.unwrap_or(ParseMode::Strict);
+        let mut collector = TurnCollector::default();
+
+        while let Some(Ok(records)) = connection.receive().await {
+            for record in records {
+                trace.received(&record);
+                match SignalRNewBingResponse::parse(&record, mode)? {
+                    SignalRNewBingResponse::Invocation(message, info) => {
+                        trace.message(&message);
+                        collector.intermediate(message, info);
+                    }
+                    SignalRNewBingResponse::StreamItem(message, info) => {
+                        trace.message(&message);
+                        trace.completed();
+                        return Ok(collector.finish(message, info, &trace));
                     }
+                    SignalRNewBingResponse::Retracted(_) => {
+                        trace.ended("blocked");
+                        return Err(ChatError::Blocked);
+                    }
+                    SignalRNewBingResponse::Disengaged(_) => {
+                        trace.ended("conversation ended");
+                        return Err(ChatError::ConversationEnded);
+                    }
+                    SignalRNewBingResponse::EndOfResponse => {
+                        trace.ended("no answer");
+                        return Err(ChatError::NoFullResponseFound);
+                    }
+                    SignalRNewBingResponse::Ping => {
+                        send_record(connection.as_mut(), &json!({"type": 6}), &trace).await?;
+                        trace.ping();
+                    }
+                    SignalRNewBingResponse::Unknown => {}
                 }
             }
         }
@@ -553,6 +1517,21 @@ impl ChatSession {
     }
 }
 
+/// Text before each part of a split prompt, the ones before the last part are longer.
+fn part_preamble(index: usize, count: usize) -> String {
+    if index < count {
+        format!(
+            "This is part {index} of {count} of my message. \
+             Do not answer yet, only reply \"Received part {index}\" and wait for all parts.\n\n"
+        )
+    } else {
+        format!(
+            "This is the last part, part {index} of {index}, of my message. \
+             Now answer the whole message.\n\n"
+        )
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum ChatError {
     #[error("Failed to get field {field_name} from {object_name}")]
@@ -574,52 +1553,272 @@ pub enum ChatError {
     NoFullResponseFound,
     #[error("No response received")]
     NoResponse,
+    #[error(transparent)]
+    RateLimited(#[from] RateLimited),
+    #[error(transparent)]
+    ImageUpload(#[from] ImageUploadError),
+    #[error(transparent)]
+    InvalidProfile(#[from] InvalidProfile),
+    #[error(transparent)]
+    InvalidSettings(#[from] SettingsError),
+    #[error("The answer has been retracted by bing")]
+    Blocked,
+    #[error("Bing has ended the conversation, start a new one")]
+    ConversationEnded,
+    #[error("The prompt has {length} characters, longer than the limit of {max_chars}")]
+    PromptTooLong { length: usize, max_chars: usize },
+}
+
+impl ChatError {
+    /// Name of the variant, eg. "Network", to count the errors by.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ChatError::GetFieldError { .. } => "GetFieldError",
+            ChatError::FieldTypeError { .. } => "FieldTypeError",
+            ChatError::Network => "Network",
+            ChatError::ParseRespond(_) => "ParseRespond",
+            ChatError::NoFullResponseFound => "NoFullResponseFound",
+            ChatError::NoResponse => "NoResponse",
+            ChatError::RateLimited(_) => "RateLimited",
+            ChatError::ImageUpload(_) => "ImageUpload",
+            ChatError::InvalidProfile(_) => "InvalidProfile",
+            ChatError::InvalidSettings(_) => "InvalidSettings",
+            ChatError::Blocked => "Blocked",
+            ChatError::ConversationEnded => "ConversationEnded",
+            ChatError::PromptTooLong { .. } => "PromptTooLong",
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, ChatError>;
-pub type ChatStream = Pin<Box<dyn Stream<Item = Result<NewBingResponseMessage>>>>;
+pub type ChatEventStream = Pin<Box<dyn Stream<Item = Result<ChatEvent>> + Send>>;
+
+/// The answer so far, after each part of it bing sends, see [`ChatSession::chat_stream`].
+pub struct ChatStream {
+    messages: Pin<Box<dyn Stream<Item = Result<NewBingResponseMessage>> + Send>>,
+    result: Arc<Mutex<Option<TurnResult>>>,
+}
+
+impl ChatStream {
+    /// Everything bing sent during the turn, like [`ChatSession::send_message_full`] returns,
+    /// `None` until the stream ended with an answer.
+    pub fn result(&self) -> Option<TurnResult> {
+        self.result.lock().unwrap().clone()
+    }
+}
+
+impl Stream for ChatStream {
+    type Item = Result<NewBingResponseMessage>;
 
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.messages.poll_next_unpin(cx)
+    }
+}
+
+/// What the text of a streamed answer changes from the text shown so far,
+/// see [`between`](TextDelta::between).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextDelta<'a> {
+    /// The text continues the shown one with this, possibly empty.
+    Append(&'a str),
+    /// Bing rewrote the answer, eg. replaced it with another final message, the whole new text.
+    Rewrite(&'a str),
+}
+
+impl<'a> TextDelta<'a> {
+    /// Compare the text of a message of a [`ChatStream`] with the text shown so far.
+    pub fn between(shown: &str, text: &'a str) -> Self {
+        match text.strip_prefix(shown) {
+            Some(appended) => Self::Append(appended),
+            None => Self::Rewrite(text),
+        }
+    }
+}
+
+/// Report the errors of the stream to the [`ChatMetrics`].
+fn record_errors<T>(
+    stream: impl Stream<Item = Result<T>>,
+    metrics: Option<Arc<dyn ChatMetrics>>,
+) -> impl Stream<Item = Result<T>> {
+    stream.inspect(move |item| {
+        if let (Err(e), Some(metrics)) = (item, &metrics) {
+            metrics.record_error(e);
+        }
+    })
+}
+
+/// The messages of the events, failing on retracted answers and ended conversations,
+/// keeping the result of the turn.
 fn chat_stream(
-    wss: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    events: impl Stream<Item = Result<ChatEvent>>,
+    result: Arc<Mutex<Option<TurnResult>>>,
 ) -> impl Stream<Item = Result<NewBingResponseMessage>> {
     try_stream! {
-        let (mut write, mut read) = wss.split();
-            'outer:while let Some(Ok(msg)) = read.next().await {
-                if let Message::Text(text) = msg {
-                    let packs = text
-                        .split('\u{1e}')
-                        .map(|it| it.trim())
-                        .filter(|it| !it.is_empty())
-                        .collect::<Vec<_>>();
-                    for pack in packs {
-                        let response = serde_json::from_str::<SignalRNewBingResponse>(pack);
-                        let response = match response {
-                            Ok(response) => match response {
-
-                                SignalRNewBingResponse::Invocation(res)
-                                | SignalRNewBingResponse::StreamItem(res) => Ok(res),
-                                SignalRNewBingResponse::Ping => {
-                                    let mut alive_message =
-                                        serde_json::to_vec(&json!({"type": 6})).unwrap();
-                                    alive_message.push(DELIMITER);
-                                    let message = Message::Binary(alive_message);
-                                    let e =
-                                        write.send(message).await.map_err(|_| ChatError::Network);
-                                    if let Err(e) = e {
-                                        Err(e)
-                                    } else {
-                                        continue;
-                                    }
-                                }
-                                SignalRNewBingResponse::EndOfResponse => break 'outer,
-                                _ => continue,
-                            },
-                            Err(_) => continue,
-                        };
-                        let response=response?;
-                        yield response;
+        for await event in events {
+            match event? {
+                ChatEvent::Message(message) => yield message,
+                ChatEvent::Retracted { .. } => Err(ChatError::Blocked)?,
+                ChatEvent::Disengaged { .. } => Err(ChatError::ConversationEnded)?,
+                ChatEvent::Completed(turn) => *result.lock().unwrap() = Some(turn),
+            }
+        }
+    }
+}
+
+fn event_stream(
+    mut connection: Box<dyn Connection>,
+    trace: TurnTrace,
+    mode: ParseMode,
+) -> impl Stream<Item = Result<ChatEvent>> {
+    try_stream! {
+        let mut collector = TurnCollector::default();
+        let mut result = None;
+        'outer: while let Some(Ok(records)) = connection.receive().await {
+            for record in records {
+                trace.received(&record);
+                let event = match SignalRNewBingResponse::parse(&record, mode)? {
+                    SignalRNewBingResponse::Invocation(res, info) => {
+                        trace.message(&res);
+                        collector.intermediate(res.clone(), info);
+                        ChatEvent::Message(res)
                     }
-                }
+                    SignalRNewBingResponse::StreamItem(res, info) => {
+                        trace.message(&res);
+                        let collector = std::mem::take(&mut collector);
+                        result = Some(collector.finish(res.clone(), info, &trace));
+                        ChatEvent::Message(res)
+                    }
+                    SignalRNewBingResponse::Retracted(text) => {
+                        trace.ended("blocked");
+                        ChatEvent::Retracted { text }
+                    }
+                    SignalRNewBingResponse::Disengaged(text) => {
+                        trace.ended("conversation ended");
+                        ChatEvent::Disengaged { text }
+                    }
+                    SignalRNewBingResponse::Ping => {
+                        send_record(connection.as_mut(), &json!({"type": 6}), &trace).await?;
+                        trace.ping();
+                        continue;
+                    }
+                    SignalRNewBingResponse::EndOfResponse => {
+                        if result.is_none() {
+                            trace.ended("no answer");
+                        }
+                        break 'outer;
+                    }
+                    SignalRNewBingResponse::Unknown => continue,
+                };
+                yield event;
             }
+        }
+        // the connection may close after the answer without ending the response
+        if let Some(result) = result {
+            trace.completed();
+            yield ChatEvent::Completed(result);
+        }
+    }
+}
+
+/// Send a SignalR record, serialized as json.
+async fn send_record(
+    connection: &mut dyn Connection,
+    record: &impl Serialize,
+    trace: &TurnTrace,
+) -> Result<()> {
+    let record = serde_json::to_string(record)?;
+    trace.sent(&record);
+    connection.send(record).await
+}
+
+/// Parse a received SignalR record leniently, `None` for records without an event.
+pub(crate) fn parse_record(record: &str) -> Result<Option<ChatEvent>> {
+    Ok(
+        match SignalRNewBingResponse::parse(record, ParseMode::Lenient)? {
+            SignalRNewBingResponse::Invocation(message, _)
+            | SignalRNewBingResponse::StreamItem(message, _) => Some(ChatEvent::Message(message)),
+            SignalRNewBingResponse::Retracted(text) => Some(ChatEvent::Retracted { text }),
+            SignalRNewBingResponse::Disengaged(text) => Some(ChatEvent::Disengaged { text }),
+            _ => None,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argument(previous_messages: Vec<PreviousMessage>) -> Value {
+        let argument = Argument::new(
+            ConversationMeta::new("id".into(), "signature".into(), "client".into()),
+            ConversationStyle::Balanced,
+            &ClientSettings::default(),
+            true,
+            NewBingRequestMessage::new("summarize".to_string()),
+            previous_messages,
+        );
+        serde_json::to_value(argument).unwrap()
+    }
+
+    #[test]
+    fn previous_messages_payload() {
+        let document = DocumentContext::new("https://example.com/", "Example", "first second")
+            .with_limits(6, 100);
+        let argument = argument(PreviousMessage::from_document(&document));
+        let message = |i: usize, description: &str| {
+            json!({
+                "author": "user",
+                "description": description,
+                "contextType": "WebPage",
+                "messageType": "Context",
+                "messageId": format!("discover-web--page-ping-mriduna-----{i}"),
+                "sourceName": "Example",
+                "sourceUrl": "https://example.com/",
+                "privacy": "Internal",
+            })
+        };
+        assert_eq!(
+            argument["previousMessages"],
+            json!([message(0, "first"), message(1, "second")])
+        );
+    }
+
+    #[test]
+    fn no_previous_messages_without_context() {
+        let argument = argument(vec![]);
+        assert!(argument.get("previousMessages").is_none());
+        assert_eq!(argument["message"]["text"], "summarize");
+    }
+
+    #[test]
+    fn answer_without_bot_message() {
+        let response = json!({"type": 2, "item": {"messages": [{"text": "hi", "author": "user"}]}});
+        assert!(matches!(
+            deserialize_newbing_response(&response, ParseMode::Strict),
+            Err(ChatError::GetFieldError {
+                object_name: "newbing_response.item",
+                ..
+            })
+        ));
+        let message = deserialize_newbing_response(&response, ParseMode::Lenient).unwrap();
+        assert!(message.text.is_empty());
+        assert_eq!(
+            message.warnings[0].to_string(),
+            "newbing_response.item.messages[{author == bot, messageType != null}] is missing"
+        );
+    }
+
+    #[test]
+    fn text_delta() {
+        assert_eq!(TextDelta::between("", "Hi"), TextDelta::Append("Hi"));
+        assert_eq!(
+            TextDelta::between("Hi", "Hi there"),
+            TextDelta::Append(" there")
+        );
+        assert_eq!(TextDelta::between("Hi", "Hi"), TextDelta::Append(""));
+        assert_eq!(
+            TextDelta::between("Hi there", "Sorry"),
+            TextDelta::Rewrite("Sorry")
+        );
     }
 }
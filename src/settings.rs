@@ -0,0 +1,291 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{ChatMetrics, ClientProfile, FrameObserver, RateLimiter, Transport};
+use ipnet::IpNet;
+use rand::Rng;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+use thiserror::Error;
+
+/// Settings of a client, applied to both creating conversations and chatting.
+/// Stored in the dumped [`ChatSession`](crate::ChatSession).
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ClientSettings {
+    /// Language and region of the user.
+    #[serde(default)]
+    pub locale: Locale,
+    /// The browser the client pretends to be.
+    #[serde(default)]
+    pub profile: ClientProfile,
+    /// How to fill the `x-forwarded-for` header.
+    #[serde(default)]
+    pub forwarded_for: ForwardedFor,
+    /// Where to send the requests.
+    #[serde(default)]
+    pub endpoints: Endpoints,
+    /// Proxy of all the requests, eg. "http://127.0.0.1:7890",
+    /// the chat hub is reached through it with `CONNECT` unless a [`transport`](ClientSettings::transport) is set.
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// Longest prompt bing accepts, and what to do with longer ones.
+    #[serde(default)]
+    pub prompt_limit: PromptLimit,
+    /// How strictly to parse the answers, by default lenient when streaming
+    /// and strict for [`send_message`](crate::ChatSession::send_message).
+    #[serde(default)]
+    pub parse_mode: Option<ParseMode>,
+    /// Limits of the requests, not dumped with the session.
+    #[serde(skip)]
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// Hooks receiving the latency and errors of chatting, not dumped with the session.
+    #[serde(skip)]
+    pub metrics: Option<Arc<dyn ChatMetrics>>,
+    /// Observer of the raw records exchanged with the chat hub, not dumped with the session.
+    #[serde(skip)]
+    pub frame_observer: Option<Arc<dyn FrameObserver>>,
+    /// How to reach the chat hub, a websocket if not set, not dumped with the session.
+    #[serde(skip)]
+    pub transport: Option<Arc<dyn Transport>>,
+}
+
+/// How to fill the `x-forwarded-for` header.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub enum ForwardedFor {
+    /// Don't send the header.
+    Disabled,
+    /// A random address in `13.104.0.0/14`, picked for each session.
+    #[default]
+    Random,
+    /// Always use this address.
+    Fixed(IpAddr),
+    /// A random address in one of these networks, picked for each session,
+    /// there should be at least one, see [`pool`](ForwardedFor::pool).
+    Pool(#[serde(deserialize_with = "non_empty_pool")] Vec<IpNet>),
+}
+
+fn non_empty_pool<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<IpNet>, D::Error> {
+    let networks = Vec::deserialize(deserializer)?;
+    if networks.is_empty() {
+        return Err(D::Error::custom(SettingsError::EmptyPool));
+    }
+    Ok(networks)
+}
+
+impl ForwardedFor {
+    /// A random address in one of the `networks`, fail if there is none.
+    pub fn pool(networks: Vec<IpNet>) -> Result<Self, SettingsError> {
+        let pool = Self::Pool(networks);
+        pool.validate()?;
+        Ok(pool)
+    }
+
+    /// Fail for a [`Pool`](ForwardedFor::Pool) without networks, which has no address to pick.
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        match self {
+            ForwardedFor::Pool(networks) if networks.is_empty() => Err(SettingsError::EmptyPool),
+            _ => Ok(()),
+        }
+    }
+
+    /// Pick an address to send, `None` if the header should not be sent
+    /// or the pool is empty.
+    pub fn pick(&self) -> Option<IpAddr> {
+        let mut rng = rand::thread_rng();
+        match self {
+            ForwardedFor::Disabled => None,
+            ForwardedFor::Random => Some(IpAddr::V4(Ipv4Addr::new(
+                13,
+                rng.gen_range(104u8..=107u8),
+                rng.gen(),
+                rng.gen(),
+            ))),
+            ForwardedFor::Fixed(ip) => Some(*ip),
+            ForwardedFor::Pool(networks) if networks.is_empty() => None,
+            ForwardedFor::Pool(networks) => match networks[rng.gen_range(0..networks.len())] {
+                IpNet::V4(network) => {
+                    let host_bits = 32 - u32::from(network.prefix_len());
+                    let host = rng.gen::<u32>().checked_shr(32 - host_bits).unwrap_or(0);
+                    Some(IpAddr::V4(Ipv4Addr::from(
+                        u32::from(network.network()) | host,
+                    )))
+                }
+                IpNet::V6(network) => {
+                    let host_bits = 128 - u32::from(network.prefix_len());
+                    let host = rng.gen::<u128>().checked_shr(128 - host_bits).unwrap_or(0);
+                    Some(IpAddr::V6(Ipv6Addr::from(
+                        u128::from(network.network()) | host,
+                    )))
+                }
+            },
+        }
+    }
+}
+
+/// Longest prompt bing accepts, and what to do with longer ones.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct PromptLimit {
+    /// at most this many characters in one message, 2000 by default.
+    pub max_chars: usize,
+    /// what to do with longer prompts, they are rejected by default.
+    #[serde(default)]
+    pub when_exceeded: WhenTooLong,
+}
+
+impl Default for PromptLimit {
+    fn default() -> Self {
+        Self {
+            max_chars: 2000,
+            when_exceeded: WhenTooLong::default(),
+        }
+    }
+}
+
+/// What to do with a prompt longer than [`PromptLimit::max_chars`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub enum WhenTooLong {
+    /// Send it anyway, bing may refuse it.
+    Send,
+    /// Fail with [`ChatError::PromptTooLong`](crate::ChatError::PromptTooLong) before sending anything.
+    #[default]
+    Reject,
+    /// Send it in several turns, asking bing to wait for all parts before answering.
+    Split,
+}
+
+/// How to handle answers missing fields or with fields of another type.
+///
+/// Only the full answer at the end of a turn is checked, the partial answers
+/// streamed before it never fail nor have warnings.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Fail the turn with [`ChatError::GetFieldError`](crate::ChatError::GetFieldError)
+    /// or [`ChatError::FieldTypeError`](crate::ChatError::FieldTypeError).
+    Strict,
+    /// Use a default instead, reported in
+    /// [`NewBingResponseMessage::warnings`](crate::NewBingResponseMessage::warnings),
+    /// and keep the fields not parsed in [`NewBingResponseMessage::extra`](crate::NewBingResponseMessage::extra).
+    Lenient,
+}
+
+/// Urls of the bing services, can be pointed to a proxy or a stand-in server.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Endpoints {
+    /// Url for creating conversations.
+    pub create_conversation: String,
+    /// Url of the websocket chat hub.
+    pub chat_hub: String,
+    /// Url listing the conversations in the chat history.
+    pub list_conversations: String,
+    /// Url fetching the messages of a conversation.
+    pub get_conversation: String,
+    /// Url deleting a conversation.
+    pub delete_conversation: String,
+    /// Url renaming a conversation.
+    pub rename_conversation: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            create_conversation: "https://edgeservices.bing.com/edgesvc/turing/conversation/create"
+                .to_string(),
+            chat_hub: "wss://sydney.bing.com/sydney/ChatHub".to_string(),
+            list_conversations: "https://www.bing.com/turing/conversation/chats".to_string(),
+            get_conversation: "https://sydney.bing.com/sydney/GetConversation".to_string(),
+            delete_conversation: "https://sydney.bing.com/sydney/DeleteSingleConversation"
+                .to_string(),
+            rename_conversation: "https://sydney.bing.com/sydney/RenameChat".to_string(),
+        }
+    }
+}
+
+/// Language and region of the user, bing answers in this language and prefers
+/// sources in this market.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Locale {
+    /// language tag, eg. "de-DE", sent as `accept-language`.
+    pub language: String,
+    /// market of the answers, eg. "de-DE".
+    pub market: String,
+    /// region code, eg. "DE".
+    pub region: String,
+    /// where the user is, used for location related questions.
+    #[serde(default)]
+    pub location: Option<GeoLocation>,
+}
+
+impl Locale {
+    /// Create a [`Locale`] from a language tag like "ja-JP",
+    /// the market is the same tag and the region is its country part.
+    pub fn new(language: &str) -> Self {
+        let region = language
+            .split_once('-')
+            .map(|(_, region)| region)
+            .unwrap_or(language)
+            .to_uppercase();
+        Self {
+            language: language.to_string(),
+            market: language.to_string(),
+            region,
+            location: None,
+        }
+    }
+
+    /// Set the location hint of the user.
+    pub fn with_location(mut self, location: GeoLocation) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    /// Value of the `accept-language` header, eg. "de-DE,de;q=0.9,en;q=0.8".
+    pub(crate) fn accept_language(&self) -> String {
+        match self.language.split_once('-') {
+            Some((primary, _)) if primary != "en" => {
+                format!("{},{primary};q=0.9,en;q=0.8", self.language)
+            }
+            Some((primary, _)) => format!("{},{primary};q=0.9", self.language),
+            None => self.language.clone(),
+        }
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self::new("en-US")
+    }
+}
+
+/// Geographic location of the user.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GeoLocation {
+    /// latitude in degrees.
+    pub latitude: f64,
+    /// longitude in degrees.
+    pub longitude: f64,
+    /// country name, eg. "Germany".
+    #[serde(default)]
+    pub country: String,
+    /// state or province name.
+    #[serde(default)]
+    pub state: String,
+    /// city name, eg. "Berlin".
+    #[serde(default)]
+    pub city: String,
+    /// offset to UTC, in hours.
+    #[serde(default)]
+    pub timezone_offset: i32,
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum SettingsError {
+    #[error("The x-forwarded-for pool has no networks")]
+    EmptyPool,
+    #[error(
+        "A rate should allow at least one request in a non zero period, got {count} in {period:?}"
+    )]
+    InvalidRate { count: u32, period: Duration },
+}
@@ -0,0 +1,221 @@
+//! Tracing of creating conversations and chatting, no-ops without the `tracing` feature,
+//! and the [`ChatMetrics`] and [`FrameObserver`] of the turns.
+#![cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    ChatMetrics, ConversationMeta, ConversationStyle, FrameDirection, FrameObserver,
+    NewBingResponseMessage, TurnMetrics,
+};
+
+/// Span of creating a conversation.
+pub(crate) struct CreateTrace {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "tracing")]
+    start: Instant,
+}
+
+impl CreateTrace {
+    pub(crate) fn new(account: Option<&str>) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!("create_conversation", account),
+            #[cfg(feature = "tracing")]
+            start: Instant::now(),
+        }
+    }
+
+    pub(crate) fn finished<E: std::fmt::Display>(&self, result: &Result<ConversationMeta, E>) {
+        #[cfg(feature = "tracing")]
+        {
+            let latency_ms = self.start.elapsed().as_millis() as u64;
+            match result {
+                Ok(meta) => tracing::debug!(
+                    parent: &self.span,
+                    conversation_id = %meta.conversation_id,
+                    signature = %redact(&meta.conversation_signature),
+                    latency_ms,
+                    "conversation created"
+                ),
+                Err(e) => tracing::warn!(
+                    parent: &self.span,
+                    latency_ms,
+                    error = %e,
+                    "failed to create conversation"
+                ),
+            }
+        }
+    }
+}
+
+/// Span, [`ChatMetrics`] and [`FrameObserver`] of a turn, from connecting to the chat hub to the end of the response.
+/// A turn dropped before it [`completed`](TurnTrace::completed) or [`ended`](TurnTrace::ended)
+/// is traced as interrupted.
+pub(crate) struct TurnTrace {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    metrics: Option<Arc<dyn ChatMetrics>>,
+    observer: Option<Arc<dyn FrameObserver>>,
+    start: Instant,
+    time_to_first_token: OnceLock<Duration>,
+    frames: AtomicUsize,
+    bytes: AtomicUsize,
+    finished: AtomicBool,
+}
+
+impl TurnTrace {
+    pub(crate) fn new(
+        meta: &ConversationMeta,
+        invocation_id: usize,
+        style: ConversationStyle,
+        metrics: Option<Arc<dyn ChatMetrics>>,
+        observer: Option<Arc<dyn FrameObserver>>,
+    ) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                "chat_turn",
+                conversation_id = %meta.conversation_id,
+                signature = %redact(&meta.conversation_signature),
+                invocation_id,
+                style = %style,
+            ),
+            metrics,
+            observer,
+            start: Instant::now(),
+            time_to_first_token: OnceLock::new(),
+            frames: AtomicUsize::new(0),
+            bytes: AtomicUsize::new(0),
+            finished: AtomicBool::new(false),
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    fn latency_ms(&self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+
+    pub(crate) fn connected(&self) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(parent: &self.span, latency_ms = self.latency_ms(), "connected to chat hub");
+    }
+
+    pub(crate) fn handshake(&self) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(parent: &self.span, latency_ms = self.latency_ms(), "handshake done");
+    }
+
+    /// A part of the answer received, the first one with text is the first token.
+    pub(crate) fn message(&self, message: &NewBingResponseMessage) {
+        #[cfg(feature = "tracing")]
+        for warning in &message.warnings {
+            tracing::warn!(parent: &self.span, %warning, "answer not fully parsed");
+        }
+        if message.text.is_empty() || self.time_to_first_token.get().is_some() {
+            return;
+        }
+        let latency = self.start.elapsed();
+        if self.time_to_first_token.set(latency).is_ok() {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(parent: &self.span, latency_ms = self.latency_ms(), "first token");
+            if let Some(metrics) = &self.metrics {
+                metrics.record_time_to_first_token(latency);
+            }
+        }
+    }
+
+    pub(crate) fn time_to_first_token(&self) -> Option<Duration> {
+        self.time_to_first_token.get().copied()
+    }
+
+    pub(crate) fn sent(&self, record: &str) {
+        if let Some(observer) = &self.observer {
+            observer.on_frame(FrameDirection::Sent, record);
+        }
+    }
+
+    /// A SignalR record received, one of the records in a websocket message.
+    pub(crate) fn received(&self, record: &str) {
+        // with its delimiter
+        let bytes = record.len() + 1;
+        let frame = self.frames.fetch_add(1, Ordering::Relaxed) + 1;
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            parent: &self.span,
+            frame,
+            bytes,
+            latency_ms = self.latency_ms(),
+            "record received"
+        );
+        if let Some(observer) = &self.observer {
+            observer.on_frame(FrameDirection::Received, record);
+        }
+    }
+
+    pub(crate) fn ping(&self) {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(parent: &self.span, latency_ms = self.latency_ms(), "ping, pong sent");
+    }
+
+    /// The turn finished with an answer, reported to the [`ChatMetrics`].
+    pub(crate) fn completed(&self) {
+        if self.finished.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let metrics = TurnMetrics {
+            time_to_first_token: self.time_to_first_token.get().copied(),
+            latency: self.start.elapsed(),
+            frames: self.frames.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+        };
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            parent: &self.span,
+            frames = metrics.frames,
+            bytes = metrics.bytes,
+            latency_ms = self.latency_ms(),
+            "turn completed"
+        );
+        if let Some(chat_metrics) = &self.metrics {
+            chat_metrics.record_turn(&metrics);
+        }
+    }
+
+    /// The turn finished without an answer, `outcome` is eg. "blocked" or "conversation ended".
+    pub(crate) fn ended(&self, outcome: &'static str) {
+        let finished = self.finished.swap(true, Ordering::Relaxed);
+        #[cfg(feature = "tracing")]
+        if !finished {
+            tracing::warn!(
+                parent: &self.span,
+                outcome,
+                frames = self.frames.load(Ordering::Relaxed),
+                bytes = self.bytes.load(Ordering::Relaxed),
+                latency_ms = self.latency_ms(),
+                "turn ended without an answer"
+            );
+        }
+    }
+}
+
+impl Drop for TurnTrace {
+    fn drop(&mut self) {
+        self.ended("interrupted");
+    }
+}
+
+/// Keep only the start of a secret, enough to tell them apart in the logs.
+#[cfg(feature = "tracing")]
+fn redact(secret: &str) -> String {
+    let start: String = secret.chars().take(6).collect();
+    format!("{start}…")
+}
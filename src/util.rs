@@ -1,12 +1,30 @@
+use crate::{ClientSettings, CookieInFile};
+use reqwest::{cookie::Jar, Proxy};
 use std::env;
-pub fn new_reqwest_client() -> reqwest::ClientBuilder {
+
+/// A client builder with the proxy of the settings, or the ones of the environment,
+/// sending the user agent of the profile of the settings.
+pub fn new_reqwest_client(settings: &ClientSettings) -> reqwest::Result<reqwest::ClientBuilder> {
     let mut builder = reqwest::Client::builder();
+    // the first proxy matching a request is used
+    if let Some(proxy) = &settings.proxy {
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
     if let Ok(http_proxy) = env::var("HTTP_PROXY") {
-        builder = builder.proxy(reqwest::Proxy::http(http_proxy).unwrap());
+        builder = builder.proxy(Proxy::http(http_proxy)?);
     }
     if let Ok(https_proxy) = env::var("HTTPS_PROXY") {
-        builder = builder.proxy(reqwest::Proxy::https(https_proxy).unwrap());
+        builder = builder.proxy(Proxy::https(https_proxy)?);
+    }
+    Ok(builder.user_agent(&settings.profile.user_agent))
+}
+
+/// Put the cookies into a [`Jar`], scoped to `bing.com` and all its subdomains.
+pub fn cookie_jar(cookies: &[CookieInFile]) -> Jar {
+    let uri = "https://www.bing.com".parse().unwrap();
+    let cookie_jar = Jar::default();
+    for CookieInFile { name, value } in cookies {
+        cookie_jar.add_cookie_str(&format!("{name}={value}; Domain=bing.com"), &uri)
     }
-    builder = builder.user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/110.0.0.0 Safari/537.36 Edg/110.0.1587.69");
-    builder
+    cookie_jar
 }
@@ -0,0 +1,172 @@
+mod common;
+
+use std::{
+    error::Error,
+    time::{Duration, Instant},
+};
+
+use edge_gpt::{
+    Account, AccountPool, AccountPoolError, ClientSettings, ConversationMetaCreatingError,
+    ConversationStyle, CookieInFile, CookieStore, Strategy,
+};
+
+fn accounts(names: &[&str]) -> Vec<Account> {
+    names
+        .iter()
+        .map(|name| Account {
+            name: name.to_string(),
+            cookies: vec![CookieInFile {
+                name: "_U".to_string(),
+                value: name.to_string(),
+            }],
+        })
+        .collect()
+}
+
+async fn create(pool: &AccountPool) -> String {
+    let settings = common::sydney().await;
+    let session = pool
+        .create_session(ConversationStyle::Balanced, settings)
+        .await
+        .unwrap();
+    session.account().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn round_robin_order() {
+    let pool = AccountPool::new(accounts(&["a", "b", "c"]), Strategy::RoundRobin);
+    let mut used = vec![];
+    for _ in 0..4 {
+        used.push(create(&pool).await);
+    }
+    assert_eq!(used, ["a", "b", "c", "a"]);
+}
+
+#[tokio::test]
+async fn least_used_order() {
+    let pool = AccountPool::new(accounts(&["a", "b"]), Strategy::LeastUsed);
+    assert_eq!(create(&pool).await, "a");
+    assert_eq!(create(&pool).await, "b");
+    pool.mark_unhealthy("a");
+    assert_eq!(create(&pool).await, "b");
+
+    let pool = AccountPool::new(accounts(&["a", "b", "c"]), Strategy::LeastUsed);
+    let settings = common::sydney().await;
+    // concurrent creatings each reserve a different account
+    let sessions = futures_util::future::join_all(
+        (0..3).map(|_| pool.create_session(ConversationStyle::Balanced, settings.clone())),
+    )
+    .await;
+    let mut used: Vec<_> = sessions
+        .into_iter()
+        .map(|session| session.unwrap().account().unwrap().to_string())
+        .collect();
+    used.sort();
+    assert_eq!(used, ["a", "b", "c"]);
+    assert!(pool.health().iter().all(|it| it.uses == 1));
+}
+
+#[tokio::test]
+async fn no_healthy_account() {
+    let pool = AccountPool::new(accounts(&["a", "b"]), Strategy::RoundRobin);
+    pool.mark_unhealthy("a");
+    pool.mark_unhealthy("b");
+    let settings = common::sydney().await;
+    let result = pool
+        .create_session(ConversationStyle::Balanced, settings)
+        .await;
+    assert!(matches!(
+        result,
+        Err(AccountPoolError::NoHealthyAccount(None))
+    ));
+}
+
+#[tokio::test]
+async fn cooldown_doubles_for_each_failure() {
+    let cooldown = Duration::from_secs(60);
+    let pool = AccountPool::new(accounts(&["a"]), Strategy::RoundRobin).with_cooldown(cooldown);
+    for failures in 1..=3u32 {
+        let before = Instant::now();
+        pool.mark_unhealthy("a");
+        let health = &pool.health()[0];
+        assert_eq!(health.failures, failures as usize);
+        let until = health.unhealthy_until.unwrap();
+        let expected = cooldown * (1 << (failures - 1));
+        assert!(until >= before + expected && until <= Instant::now() + expected);
+    }
+}
+
+#[tokio::test]
+async fn unhealthy_account_recovers() {
+    let pool = AccountPool::new(accounts(&["a", "b"]), Strategy::RoundRobin)
+        .with_cooldown(Duration::from_millis(100));
+    pool.mark_unhealthy("a");
+    assert_eq!(create(&pool).await, "b");
+    assert_eq!(create(&pool).await, "b");
+
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(create(&pool).await, "a");
+    let health = &pool.health()[0];
+    assert_eq!(health.failures, 0);
+    assert!(health.unhealthy_until.is_none());
+}
+
+/// A pool whose cookies are sent to the stand-in, the `_U` of each account is its name.
+fn scoped_pool(names: &[&str], settings: &ClientSettings) -> AccountPool {
+    let base_url = common::base_url(settings);
+    AccountPool::new(accounts(names), Strategy::RoundRobin).with_cookie_stores(|account| {
+        CookieStore::new(&account.cookies)
+            .with_base_url(&base_url)
+            .unwrap()
+    })
+}
+
+#[tokio::test]
+async fn unauthorized_account_is_skipped() {
+    let settings = common::sydney().await;
+    let pool = scoped_pool(&["unauthorized", "b"], &settings);
+    let session = pool
+        .create_session(ConversationStyle::Balanced, settings)
+        .await
+        .unwrap();
+    assert_eq!(session.account(), Some("b"));
+
+    let health = pool.health();
+    assert_eq!((health[0].uses, health[0].failures), (0, 1));
+    assert!(health[0].unhealthy_until.is_some());
+    assert_eq!((health[1].uses, health[1].failures), (1, 0));
+}
+
+#[tokio::test]
+async fn no_healthy_account_after_rejections() {
+    let settings = common::sydney().await;
+    let pool = scoped_pool(&["unauthorized"], &settings);
+    let error = pool
+        .create_session(ConversationStyle::Balanced, settings)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        AccountPoolError::NoHealthyAccount(Some(ConversationMetaCreatingError::Unauthorized))
+    ));
+    let source = error.source().unwrap();
+    assert!(matches!(
+        source.downcast_ref(),
+        Some(ConversationMetaCreatingError::Unauthorized)
+    ));
+}
+
+#[tokio::test]
+async fn accounts_keep_their_rotated_cookies() {
+    let settings = common::sydney().await;
+    let pool = scoped_pool(&["a", "b"], &settings);
+    for _ in 0..3 {
+        pool.create_session(ConversationStyle::Balanced, settings.clone())
+            .await
+            .unwrap();
+    }
+    let cookies = |name| pool.cookie_store(name).unwrap().cookies()[0].value.clone();
+    assert_eq!(cookies("a"), "a-rotated-rotated");
+    assert_eq!(cookies("b"), "b-rotated");
+    assert!(pool.cookie_store("c").is_none());
+}
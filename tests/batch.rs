@@ -0,0 +1,154 @@
+mod common;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use edge_gpt::{BatchRunner, ChatMetrics, ClientSettings, ConversationStyle};
+
+#[derive(Debug, Default)]
+struct Retries(AtomicUsize);
+
+impl ChatMetrics for Retries {
+    fn record_retry(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+async fn counting_retries() -> (ClientSettings, Arc<Retries>) {
+    let retries = Arc::new(Retries::default());
+    let mut settings = common::sydney().await;
+    settings.metrics = Some(retries.clone());
+    (settings, retries)
+}
+
+#[tokio::test]
+async fn run_prompts_concurrently() {
+    let runner = BatchRunner::new(ConversationStyle::Balanced, &[], common::sydney().await)
+        .with_concurrency(2)
+        .with_prompts_per_conversation(2);
+    let prompts = ["one", "two", "forbidden", "four", "five"];
+    let items = runner.run(&prompts).await;
+
+    let prompts_sent: Vec<_> = items.iter().map(|it| it.prompt.as_str()).collect();
+    assert_eq!(prompts_sent, prompts);
+    assert!(items[2].answer.is_none());
+    assert!(items[2].error.as_deref().unwrap().contains("retracted"));
+    for item in items.iter().filter(|it| it.index != 2) {
+        let answer = item.answer.as_ref().unwrap();
+        assert!(answer
+            .text
+            .starts_with(&format!("You said: {}", item.prompt)));
+        assert!(answer.text.ends_with("(turn 0)") || answer.text.ends_with("(turn 1)"));
+    }
+}
+
+#[tokio::test]
+async fn resume_from_checkpoint() {
+    let path = std::env::temp_dir().join(format!("edge-gpt-batch-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let (settings, retries) = counting_retries().await;
+    let runner = BatchRunner::new(ConversationStyle::Balanced, &[], settings);
+
+    let items = runner
+        .run_with_checkpoint(&["one", "forbidden"], &path)
+        .await
+        .unwrap();
+    assert!(items[1].error.is_some());
+    assert_eq!(retries.0.load(Ordering::Relaxed), 0);
+
+    // the failed prompt is sent again, the answered one is not
+    let items = runner
+        .run_with_checkpoint(&["one", "forbidden", "three"], &path)
+        .await
+        .unwrap();
+    let lines = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(lines.lines().count(), 4);
+    let indices: Vec<_> = items.iter().map(|it| it.index).collect();
+    assert_eq!(indices, [0, 1, 2]);
+    assert_eq!(
+        items[2].answer.as_ref().unwrap().text,
+        "You said: three (turn 0)"
+    );
+    assert_eq!(retries.0.load(Ordering::Relaxed), 1);
+}
+
+#[tokio::test]
+async fn new_conversation_after_failure_is_a_retry() {
+    let (settings, retries) = counting_retries().await;
+    let runner = BatchRunner::new(ConversationStyle::Balanced, &[], settings)
+        .with_concurrency(1)
+        .with_prompts_per_conversation(3);
+    let items = runner.run(&["one", "forbidden", "three"]).await;
+
+    assert!(items[1].answer.is_none());
+    assert_eq!(
+        items[2].answer.as_ref().unwrap().text,
+        "You said: three (turn 0)"
+    );
+    assert_eq!(retries.0.load(Ordering::Relaxed), 1);
+}
+
+fn checkpoint_path(name: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "edge-gpt-batch-{name}-{}.jsonl",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[tokio::test]
+async fn changed_prompts_are_sent_again() {
+    let path = checkpoint_path("changed");
+    let runner = BatchRunner::new(ConversationStyle::Balanced, &[], common::sydney().await);
+    runner
+        .run_with_checkpoint(&["one", "two"], &path)
+        .await
+        .unwrap();
+
+    let items = runner
+        .run_with_checkpoint(&["one", "deux"], &path)
+        .await
+        .unwrap();
+    let lines = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(lines.lines().count(), 3);
+    assert_eq!(
+        items[1].answer.as_ref().unwrap().text,
+        "You said: deux (turn 0)"
+    );
+}
+
+#[tokio::test]
+async fn resume_an_interrupted_checkpoint() {
+    let path = checkpoint_path("interrupted");
+    let runner = BatchRunner::new(ConversationStyle::Balanced, &[], common::sydney().await);
+    runner.run_with_checkpoint(&["one"], &path).await.unwrap();
+    // the answer to the second prompt was being written
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    std::io::Write::write_all(&mut file, br#"{"index":1,"prompt":"tw"#).unwrap();
+
+    let items = runner
+        .run_with_checkpoint(&["one", "two"], &path)
+        .await
+        .unwrap();
+    assert_eq!(
+        items[1].answer.as_ref().unwrap().text,
+        "You said: two (turn 0)"
+    );
+    // the line cut short is dropped, later runs read the file
+    let items = runner
+        .run_with_checkpoint(&["one", "two"], &path)
+        .await
+        .unwrap();
+    let lines = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(lines.lines().count(), 2);
+    assert_eq!(items.len(), 2);
+}
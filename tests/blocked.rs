@@ -0,0 +1,58 @@
+mod common;
+
+use edge_gpt::{ChatError, ChatEvent, ChatSession, ConversationStyle, StreamExt};
+
+async fn session() -> ChatSession {
+    let settings = common::sydney().await;
+    ChatSession::create_with_settings(ConversationStyle::Balanced, &[], settings)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn retracted_answer_event() {
+    let mut session = session().await;
+    let events: Vec<_> = session
+        .event_stream("something forbidden")
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    let events: Vec<_> = events.into_iter().map(Result::unwrap).collect();
+    assert!(matches!(&events[0], ChatEvent::Message(message) if message.text == "You"));
+    assert!(matches!(
+        events.last(),
+        Some(ChatEvent::Retracted { text }) if text == "Sorry, let's talk about something else."
+    ));
+}
+
+#[tokio::test]
+async fn retracted_answer_fails_chat_stream() {
+    let mut session = session().await;
+    let results: Vec<_> = session
+        .chat_stream("something forbidden")
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert!(results[0].is_ok());
+    assert!(matches!(results.last(), Some(Err(ChatError::Blocked))));
+}
+
+#[tokio::test]
+async fn disengaged_conversation() {
+    let mut session = session().await;
+    let result = session.send_message("goodbye").await;
+    assert!(matches!(result, Err(ChatError::ConversationEnded)));
+
+    let events: Vec<_> = session
+        .event_stream("goodbye")
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert!(matches!(
+        events.last(),
+        Some(Ok(ChatEvent::Disengaged { text })) if text.contains("new topic")
+    ));
+}
@@ -0,0 +1,384 @@
+//! A stand-in for bing chat, replying "You said: {text} (turn {invocationId})",
+//! images uploaded to it are referenced by their size, eg. "/images/blob?bcid=16x8".
+//! It retracts the answers to prompts containing "forbidden", and ends the conversation
+//! on "goodbye". The final answer to prompts containing "rewrite" says "You wrote" instead.
+//! Creating a conversation rotates the `_U` cookie, and the home page sets `SRCHHPGUSR`.
+//! Creating one with `_U=unauthorized` is rejected with 401.
+//! The chat history holds "history-1", with 2 turns, and "history-2",
+//! fetching the messages of "broken" returns no result.
+//! The headers of the create requests and the chat requests it received are logged.
+
+// each test uses only some of the helpers
+#![allow(dead_code)]
+
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use edge_gpt::{
+    ChatSession, ClientSettings, ConversationMeta, ConversationStyle, Endpoints, FrameDirection,
+    RecordedFrame, ReplayTransport, Transport,
+};
+use futures_util::{SinkExt, StreamExt};
+use image::{ImageOutputFormat, RgbImage};
+use serde_json::{json, Value};
+use tokio_tungstenite::{
+    accept_hdr_async,
+    tungstenite::{handshake::server::Request, Message},
+};
+
+const DELIMITER: char = '\u{1e}';
+
+/// A websocket message of the record.
+fn record(value: Value) -> Message {
+    Message::Text(format!("{value}{DELIMITER}"))
+}
+
+fn log_headers(headers: &HeaderMap) -> Value {
+    headers
+        .iter()
+        .map(|(name, value)| (name.to_string(), json!(value.to_str().unwrap_or_default())))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+/// Requests the stand-in received, `{"create": {header: value}}` for creating a conversation,
+/// `{"chat": request, "headers": {header: value}}` for a chat request,
+/// `{"home": {header: value}}` for visiting the home page and
+/// `{"kblob": {header: value}, "knowledgeRequest": request}` for uploading an image.
+pub type Requests = Arc<Mutex<Vec<Value>>>;
+
+async fn create(Extension(requests): Extension<Requests>, headers: HeaderMap) -> Response {
+    requests
+        .lock()
+        .unwrap()
+        .push(json!({ "create": log_headers(&headers) }));
+    static CREATED: AtomicUsize = AtomicUsize::new(0);
+    let n = CREATED.fetch_add(1, Ordering::Relaxed);
+    let user = headers
+        .get(header::COOKIE)
+        .and_then(|it| it.to_str().ok())
+        .and_then(|it| it.split("; ").find_map(|it| it.strip_prefix("_U=")))
+        .unwrap_or("anonymous");
+    if user == "unauthorized" {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let cookie = [(header::SET_COOKIE, format!("_U={user}-rotated; Path=/"))];
+    let meta = Json(json!({
+        "conversationId": format!("conversation-{n}"),
+        "clientId": "client",
+        "conversationSignature": "signature",
+        "result": {"value": "Success", "message": null},
+    }));
+    (cookie, meta).into_response()
+}
+
+async fn home(Extension(requests): Extension<Requests>, headers: HeaderMap) -> impl IntoResponse {
+    requests
+        .lock()
+        .unwrap()
+        .push(json!({ "home": log_headers(&headers) }));
+    [(header::SET_COOKIE, "SRCHHPGUSR=touched; Path=/")]
+}
+
+type History = Arc<Mutex<Vec<Value>>>;
+
+fn not_found() -> Json<Value> {
+    Json(json!({"result": {"value": "NotFound", "message": "Conversation not found"}}))
+}
+
+async fn list_chats(State(history): State<History>) -> Json<Value> {
+    Json(json!({
+        "clientId": "client",
+        "chats": *history.lock().unwrap(),
+        "result": {"value": "Success", "message": null},
+    }))
+}
+
+async fn get_conversation(Query(query): Query<HashMap<String, String>>) -> Json<Value> {
+    match query["conversationId"].as_str() {
+        "history-1" => Json(json!({
+            "messages": [
+                {"author": "user", "text": "hi"},
+                {"author": "bot", "text": "Hello"},
+                {"author": "bot", "text": "Searching", "messageType": "InternalSearchQuery"},
+                {"author": "user", "text": "again"},
+                {"author": "bot", "text": "Hello again"},
+            ],
+            "result": {"value": "Success", "message": null},
+        })),
+        "broken" => Json(json!({})),
+        _ => not_found(),
+    }
+}
+
+async fn delete_conversation(
+    State(history): State<History>,
+    Json(body): Json<Value>,
+) -> Json<Value> {
+    let mut history = history.lock().unwrap();
+    let Some(i) = history
+        .iter()
+        .position(|it| it["conversationId"] == body["conversationId"])
+    else {
+        return not_found();
+    };
+    history.remove(i);
+    Json(json!({"result": {"value": "Success", "message": null}}))
+}
+
+async fn rename_chat(State(history): State<History>, Json(body): Json<Value>) -> Json<Value> {
+    let mut history = history.lock().unwrap();
+    let Some(chat) = history
+        .iter_mut()
+        .find(|it| it["conversationId"] == body["conversationId"])
+    else {
+        return not_found();
+    };
+    chat["chatName"] = body["chatName"].clone();
+    Json(json!({"result": {"value": "Success", "message": null}}))
+}
+
+async fn kblob(
+    Extension(requests): Extension<Requests>,
+    headers: HeaderMap,
+    body: String,
+) -> Json<Value> {
+    let knowledge_request = body
+        .split("name=\"knowledgeRequest\"\r\n\r\n")
+        .nth(1)
+        .and_then(|it| it.split("\r\n").next())
+        .unwrap();
+    requests.lock().unwrap().push(json!({
+        "kblob": log_headers(&headers),
+        "knowledgeRequest": serde_json::from_str::<Value>(knowledge_request).unwrap(),
+    }));
+    let image = body
+        .split("name=\"imageBase64\"\r\n\r\n")
+        .nth(1)
+        .and_then(|it| it.split("\r\n").next())
+        .unwrap();
+    let image = image::load_from_memory(&STANDARD.decode(image).unwrap()).unwrap();
+    Json(json!({
+        "blobId": "original",
+        "processedBlobId": format!("{}x{}", image.width(), image.height()),
+    }))
+}
+
+// the error of the handshake callback is defined by tungstenite
+#[allow(clippy::result_large_err)]
+async fn chat(tcp: tokio::net::TcpStream, requests: Requests) {
+    let mut headers = Value::Null;
+    let mut ws = accept_hdr_async(tcp, |request: &Request, response| {
+        headers = log_headers(request.headers());
+        Ok(response)
+    })
+    .await
+    .unwrap();
+    // handshake
+    ws.next().await.unwrap().unwrap();
+    ws.send(Message::Text(format!("{{}}{DELIMITER}")))
+        .await
+        .unwrap();
+    let request = loop {
+        let message = ws.next().await.unwrap().unwrap().into_data();
+        let request = String::from_utf8(message)
+            .unwrap()
+            .split(DELIMITER)
+            .filter(|it| !it.trim().is_empty())
+            .map(|it| serde_json::from_str::<Value>(it).unwrap())
+            .find(|it| it["type"] == 4);
+        if let Some(request) = request {
+            break request;
+        }
+    };
+    requests
+        .lock()
+        .unwrap()
+        .push(json!({ "chat": request.clone(), "headers": headers }));
+    let message = &request["arguments"][0]["message"];
+    let text = match message["imageUrl"].as_str() {
+        Some(image_url) => format!("{} {image_url}", message["text"].as_str().unwrap()),
+        None => message["text"].as_str().unwrap().to_string(),
+    };
+    let invocation_id = request["invocationId"].as_str().unwrap().to_string();
+    if text == "goodbye" {
+        ws.send(record(json!({
+            "type": 2,
+            "invocationId": invocation_id,
+            "item": {"messages": [
+                {"text": text, "author": "user"},
+                {
+                    "text": "It might be time to move onto a new topic.",
+                    "author": "bot",
+                    "messageType": "Disengaged",
+                },
+            ]},
+        })))
+        .await
+        .unwrap();
+        return;
+    }
+    let reply = format!("You said: {text} (turn {invocation_id})");
+    let words: Vec<&str> = reply.split(' ').collect();
+    for i in 1..=words.len() {
+        let partial = words[..i].join(" ");
+        ws.send(record(json!({
+            "type": 1,
+            "target": "update",
+            "arguments": [{"messages": [{"text": partial, "author": "bot"}]}],
+        })))
+        .await
+        .unwrap();
+    }
+    let mut answer = json!({
+        "text": reply,
+        "author": "bot",
+        "suggestedResponses": [
+            {"text": "Tell me more", "messageId": "suggestion-1", "messageType": "Suggestion"},
+        ],
+        "sourceAttributions": [{"seeMoreUrl": "https://example.com/"}],
+    });
+    if text.contains("rewrite") {
+        answer["text"] = json!(reply.replacen("You said", "You wrote", 1));
+    }
+    if text.contains("forbidden") {
+        answer["text"] = json!("Sorry, let's talk about something else.");
+        answer["hiddenText"] = json!(reply);
+        answer["offense"] = json!("OffenseTrigger");
+        ws.send(record(json!({
+            "type": 1,
+            "target": "update",
+            "arguments": [{"messages": [answer]}],
+        })))
+        .await
+        .unwrap();
+        answer["contentOrigin"] = json!("Apology");
+    }
+    ws.send(record(json!({
+        "type": 2,
+        "invocationId": invocation_id,
+        "item": {"messages": [{"text": text, "author": "user"}, answer]},
+    })))
+    .await
+    .unwrap();
+    ws.send(record(json!({"type": 3, "invocationId": invocation_id})))
+        .await
+        .unwrap();
+}
+
+/// The url of the stand-in, eg. for scoping a [`CookieStore`](edge_gpt::CookieStore) to it.
+pub fn base_url(settings: &ClientSettings) -> String {
+    settings
+        .endpoints
+        .create_conversation
+        .trim_end_matches("create")
+        .to_string()
+}
+
+/// Start the stand-in, return the settings pointing to it.
+pub async fn sydney() -> ClientSettings {
+    sydney_with_requests().await.0
+}
+
+/// Start the stand-in, return the settings pointing to it and the requests it receives.
+pub async fn sydney_with_requests() -> (ClientSettings, Requests) {
+    let requests = Requests::default();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let http_addr = listener.local_addr().unwrap();
+    let history: History = Arc::new(Mutex::new(vec![
+        json!({"conversationId": "history-1", "conversationSignature": "signature-1", "chatName": "Rust", "tone": "Balanced"}),
+        json!({"conversationId": "history-2", "conversationSignature": "signature-2", "chatName": "Go", "tone": "Creative"}),
+    ]));
+    let app = Router::new()
+        .route("/", get(home))
+        .route("/create", get(create))
+        .route("/images/kblob", post(kblob))
+        .route("/turing/conversation/chats", get(list_chats))
+        .route("/sydney/GetConversation", get(get_conversation))
+        .route(
+            "/sydney/DeleteSingleConversation",
+            post(delete_conversation),
+        )
+        .route("/sydney/RenameChat", post(rename_chat))
+        .layer(Extension(requests.clone()))
+        .with_state(history);
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service()),
+    );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let ws_addr = listener.local_addr().unwrap();
+    let chat_requests = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((tcp, _)) = listener.accept().await {
+            tokio::spawn(chat(tcp, chat_requests.clone()));
+        }
+    });
+
+    let settings = ClientSettings {
+        endpoints: Endpoints {
+            create_conversation: format!("http://{http_addr}/create"),
+            chat_hub: format!("ws://{ws_addr}/sydney/ChatHub"),
+            list_conversations: format!("http://{http_addr}/turing/conversation/chats"),
+            get_conversation: format!("http://{http_addr}/sydney/GetConversation"),
+            delete_conversation: format!("http://{http_addr}/sydney/DeleteSingleConversation"),
+            rename_conversation: format!("http://{http_addr}/sydney/RenameChat"),
+        },
+        ..ClientSettings::default()
+    };
+    (settings, requests)
+}
+
+/// A black png image of the size.
+pub fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut content = Cursor::new(vec![]);
+    RgbImage::new(width, height)
+        .write_to(&mut content, ImageOutputFormat::Png)
+        .unwrap();
+    content.into_inner()
+}
+
+/// A frame of a recording, sent or received at the start of it.
+pub fn frame(direction: FrameDirection, record: Value) -> RecordedFrame {
+    RecordedFrame {
+        direction,
+        time: 0,
+        record: record.to_string(),
+    }
+}
+
+/// A session in a made up conversation, talking to bing through the transport.
+pub fn session_with(transport: impl Transport + 'static) -> ChatSession {
+    let mut session = ChatSession::from_meta(
+        ConversationMeta::new("id".into(), "signature".into(), "client".into()),
+        ConversationStyle::Balanced,
+        0,
+    );
+    session.set_settings(ClientSettings {
+        transport: Some(Arc::new(transport)),
+        ..ClientSettings::default()
+    });
+    session
+}
+
+/// A session replaying the frames, see [`session_with`].
+pub fn replay_session(frames: &[RecordedFrame]) -> ChatSession {
+    session_with(ReplayTransport::new(frames))
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use edge_gpt::{
+    ConversationManager, ConversationManagingError, ConversationMeta, ConversationStyle,
+};
+
+async fn manager() -> ConversationManager {
+    ConversationManager::new_with_settings(&[], &common::sydney().await).unwrap()
+}
+
+fn meta(conversation_id: &str) -> ConversationMeta {
+    ConversationMeta::new(
+        conversation_id.to_string(),
+        "signature".to_string(),
+        "client".to_string(),
+    )
+}
+
+#[tokio::test]
+async fn list_and_read_conversations() {
+    let manager = manager().await;
+    let list = manager.list().await.unwrap();
+    let names: Vec<_> = list
+        .conversations
+        .iter()
+        .map(|it| it.chat_name.as_deref().unwrap())
+        .collect();
+    assert_eq!(names, ["Rust", "Go"]);
+    assert_eq!(list.meta(&list.conversations[0]).client_id, "client");
+
+    let messages = manager.messages(&meta("history-1")).await.unwrap();
+    assert_eq!(messages.len(), 5);
+    assert_eq!(
+        messages[2].message_type.as_deref(),
+        Some("InternalSearchQuery")
+    );
+
+    let session = manager
+        .resume(&list, &list.conversations[0], ConversationStyle::Creative)
+        .await
+        .unwrap();
+    assert_eq!(serde_json::to_value(&session).unwrap()["invocation_id"], 2);
+}
+
+#[tokio::test]
+async fn rename_and_delete_conversations() {
+    let manager = manager().await;
+    manager.rename(&meta("history-2"), "Zig").await.unwrap();
+    let list = manager.list().await.unwrap();
+    assert_eq!(list.conversations[1].chat_name.as_deref(), Some("Zig"));
+
+    manager.delete(&meta("history-1")).await.unwrap();
+    assert_eq!(manager.list().await.unwrap().conversations.len(), 1);
+    assert_eq!(manager.delete_all().await.unwrap(), 1);
+    assert!(manager.list().await.unwrap().conversations.is_empty());
+}
+
+#[tokio::test]
+async fn rejected_and_missing_results() {
+    let manager = manager().await;
+    let result = manager.delete(&meta("unknown")).await;
+    assert!(
+        matches!(result, Err(ConversationManagingError::Rejected(message)) if message == "Conversation not found")
+    );
+    let result = manager.messages(&meta("broken")).await;
+    assert!(matches!(result, Err(ConversationManagingError::NoResult)));
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use edge_gpt::{
+    ChatSession, ClientSettings, ConversationManager, ConversationStyle, CookieInFile, CookieStore,
+};
+
+fn cookie(name: &str, value: &str) -> CookieInFile {
+    CookieInFile {
+        name: name.to_string(),
+        value: value.to_string(),
+    }
+}
+
+/// A store of `_U=user` scoped to the stand-in.
+fn store(settings: &ClientSettings) -> CookieStore {
+    CookieStore::new(&[cookie("_U", "user")])
+        .with_base_url(&common::base_url(settings))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn keep_rotated_cookies() {
+    let settings = common::sydney().await;
+    let updates = Arc::new(Mutex::new(vec![]));
+    let store = store(&settings).with_on_update({
+        let updates = updates.clone();
+        move |cookies| updates.lock().unwrap().push(cookies.to_vec())
+    });
+
+    ChatSession::create_with_store(ConversationStyle::Balanced, &store, settings)
+        .await
+        .unwrap();
+    assert_eq!(store.cookies(), [cookie("_U", "user-rotated")]);
+    assert_eq!(*updates.lock().unwrap(), [[cookie("_U", "user-rotated")]]);
+}
+
+#[tokio::test]
+async fn reset_with_rotated_cookies() {
+    let settings = common::sydney().await;
+    let store = store(&settings);
+    let mut session = ChatSession::create_with_store(ConversationStyle::Balanced, &store, settings)
+        .await
+        .unwrap();
+
+    session.reset().await.unwrap();
+    assert_eq!(
+        session.cookie_store().unwrap().cookies(),
+        [cookie("_U", "user-rotated-rotated")]
+    );
+    assert_eq!(store.cookies(), [cookie("_U", "user-rotated-rotated")]);
+}
+
+#[tokio::test]
+async fn touch_refreshes_cookies() {
+    let settings = common::sydney().await;
+    let updates = Arc::new(Mutex::new(0));
+    let store = store(&settings).with_on_update({
+        let updates = updates.clone();
+        move |_| *updates.lock().unwrap() += 1
+    });
+
+    assert!(store.touch(&settings).await.unwrap());
+    assert!(!store.touch(&settings).await.unwrap());
+    assert_eq!(
+        store.cookies(),
+        [cookie("SRCHHPGUSR", "touched"), cookie("_U", "user")]
+    );
+    assert_eq!(*updates.lock().unwrap(), 1);
+}
+
+#[tokio::test]
+async fn save_cookies() {
+    let store = CookieStore::new(&[cookie("_U", "user"), cookie("MUID", "muid")]);
+    let path = std::env::temp_dir().join(format!("edge-gpt-cookies-{}.json", std::process::id()));
+    store.save(&path).unwrap();
+    let file = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let read: Vec<CookieInFile> = serde_json::from_str(&file).unwrap();
+    assert_eq!(read, [cookie("MUID", "muid"), cookie("_U", "user")]);
+}
+
+#[tokio::test]
+async fn base_url_scopes_the_cookies() {
+    let (settings, requests) = common::sydney_with_requests().await;
+    // scoped to bing.com, the stand-in gets no cookies
+    let bing = CookieStore::new(&[cookie("_U", "user")]);
+    ChatSession::create_with_store(ConversationStyle::Balanced, &bing, settings.clone())
+        .await
+        .unwrap();
+    assert_eq!(bing.cookies(), [cookie("_U", "user")]);
+
+    let scoped = store(&settings);
+    ChatSession::create_with_store(ConversationStyle::Balanced, &scoped, settings)
+        .await
+        .unwrap();
+    assert_eq!(scoped.cookies(), [cookie("_U", "user-rotated")]);
+
+    let requests = requests.lock().unwrap();
+    assert!(requests[0]["create"].get("cookie").is_none());
+    assert_eq!(requests[1]["create"]["cookie"], "_U=user");
+}
+
+#[tokio::test]
+async fn resumed_sessions_share_the_store() {
+    let settings = common::sydney().await;
+    let store = store(&settings);
+    let manager = ConversationManager::new_with_store(&store, &settings).unwrap();
+    let list = manager.list().await.unwrap();
+    let mut session = manager
+        .resume(&list, &list.conversations[0], ConversationStyle::Balanced)
+        .await
+        .unwrap();
+
+    session.reset().await.unwrap();
+    assert_eq!(store.cookies(), [cookie("_U", "user-rotated")]);
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use edge_gpt::{
+    ChatSession, ConversationMeta, ConversationMetaCreatingError, ConversationStyle, ForwardedFor,
+    SettingsError,
+};
+use serde_json::{json, Value};
+
+fn dump() -> Value {
+    let session = ChatSession::new(
+        ConversationMeta::new("id".into(), "signature".into(), "client".into()),
+        ConversationStyle::Balanced,
+        0,
+        "uuid".into(),
+        "13.104.0.1".into(),
+    );
+    serde_json::to_value(&session).unwrap()
+}
+
+#[test]
+fn restored_address_is_validated() {
+    let mut dump = dump();
+    assert_eq!(dump["ip"], "13.104.0.1");
+    serde_json::from_value::<ChatSession>(dump.clone()).unwrap();
+
+    dump["ip"] = json!("not-an-ip");
+    assert!(serde_json::from_value::<ChatSession>(dump).is_err());
+}
+
+#[test]
+fn empty_pool_is_rejected() {
+    assert!(matches!(
+        ForwardedFor::pool(vec![]),
+        Err(SettingsError::EmptyPool)
+    ));
+    assert!(ForwardedFor::pool(vec!["10.0.0.0/8".parse().unwrap()]).is_ok());
+
+    let mut dump = dump();
+    dump["settings"] = json!({"forwarded_for": {"Pool": []}});
+    assert!(serde_json::from_value::<ChatSession>(dump.clone()).is_err());
+    dump["settings"] = json!({"forwarded_for": {"Pool": ["10.0.0.0/8"]}});
+    serde_json::from_value::<ChatSession>(dump).unwrap();
+}
+
+#[tokio::test]
+async fn empty_pool_fails_creating() {
+    let mut settings = common::sydney().await;
+    settings.forwarded_for = ForwardedFor::Pool(vec![]);
+    let result =
+        ChatSession::create_with_settings(ConversationStyle::Balanced, &[], settings).await;
+    assert!(matches!(
+        result,
+        Err(ConversationMetaCreatingError::InvalidSettings(
+            SettingsError::EmptyPool
+        ))
+    ));
+}
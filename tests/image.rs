@@ -0,0 +1,81 @@
+use std::{
+    net::{SocketAddr, TcpListener},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header::LOCATION, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Router,
+};
+use edge_gpt::{ImageGenerationError, ImageGenerator};
+use std::collections::HashMap;
+
+/// A stand-in for Bing Image Creator, the result is ready at the second poll.
+fn serve() -> SocketAddr {
+    async fn create(Query(query): Query<HashMap<String, String>>) -> impl IntoResponse {
+        if query["q"].contains("forbidden") {
+            return (StatusCode::OK, "This prompt has been blocked.").into_response();
+        }
+        let location = format!("/images/create?q={}&rt=4&FORM=GENCRE&id=42", query["q"]);
+        (StatusCode::FOUND, [(LOCATION, location)]).into_response()
+    }
+    async fn results(
+        State(polled): State<Arc<AtomicUsize>>,
+        Path(id): Path<String>,
+    ) -> impl IntoResponse {
+        assert_eq!(id, "42");
+        if polled.fetch_add(1, Ordering::SeqCst) == 0 {
+            return String::new();
+        }
+        concat!(
+            r#"<div><img class="mimg" src="https://th.bing.com/th/id/OIG.a?w=270&h=270" alt="a"/>"#,
+            r#"<img class="mimg" src="https://th.bing.com/th/id/OIG.b?w=270&h=270" alt="b"/>"#,
+            r#"<img src="/rp/logo.svg"/></div>"#
+        )
+        .to_string()
+    }
+    let app = Router::new()
+        .route("/images/create", post(create))
+        .route("/images/create/async/results/:id", get(results))
+        .with_state(Arc::new(AtomicUsize::new(0)));
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service()),
+    );
+    addr
+}
+
+fn generator(addr: SocketAddr) -> ImageGenerator {
+    ImageGenerator::new(&[])
+        .unwrap()
+        .with_base_url(&format!("http://{addr}"))
+        .with_polling(Duration::from_millis(10), Duration::from_secs(5))
+}
+
+#[tokio::test]
+async fn generate_images() {
+    let urls = generator(serve()).generate("a cat").await.unwrap();
+    assert_eq!(
+        urls,
+        [
+            "https://th.bing.com/th/id/OIG.a",
+            "https://th.bing.com/th/id/OIG.b"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn detect_blocked_prompt() {
+    let result = generator(serve()).generate("something forbidden").await;
+    assert!(matches!(result, Err(ImageGenerationError::Blocked)));
+}
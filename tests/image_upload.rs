@@ -0,0 +1,87 @@
+mod common;
+
+use common::png;
+use edge_gpt::{
+    ChatError, ChatSession, ConversationStyle, ImageSource, ImageUploadError, ImageUploader,
+};
+
+async fn session_and_uploader() -> (ChatSession, ImageUploader) {
+    let settings = common::sydney().await;
+    let base_url = settings
+        .endpoints
+        .create_conversation
+        .trim_end_matches("/create")
+        .to_string();
+    let session = ChatSession::create_with_settings(ConversationStyle::Balanced, &[], settings)
+        .await
+        .unwrap();
+    let uploader = ImageUploader::new_with_settings(&[], session.settings())
+        .unwrap()
+        .with_base_url(&base_url)
+        .with_max_dimension(100);
+    (session, uploader)
+}
+
+#[tokio::test]
+async fn send_message_with_image() {
+    let (mut session, uploader) = session_and_uploader().await;
+    let response = session
+        .send_message_with_image("What is it?", &uploader, ImageSource::Bytes(png(16, 8)))
+        .await
+        .unwrap();
+    assert!(response.text.starts_with("You said: What is it? http://"));
+    assert!(response.text.ends_with("/images/blob?bcid=16x8 (turn 0)"));
+}
+
+#[tokio::test]
+async fn upload_for_the_conversation() {
+    let (settings, requests) = common::sydney_with_requests().await;
+    let uploader = ImageUploader::new_with_settings(&[], &settings)
+        .unwrap()
+        .with_base_url(&common::base_url(&settings));
+    let mut session = ChatSession::create_with_settings(ConversationStyle::Precise, &[], settings)
+        .await
+        .unwrap();
+    session
+        .send_message_with_image("What is it?", &uploader, ImageSource::Bytes(png(16, 8)))
+        .await
+        .unwrap();
+
+    let requests = requests.lock().unwrap();
+    let upload = requests
+        .iter()
+        .find(|it| it.get("kblob").is_some())
+        .unwrap();
+    let convo_data = &upload["knowledgeRequest"]["knowledgeRequest"]["convoData"];
+    assert!(convo_data["convoid"]
+        .as_str()
+        .unwrap()
+        .starts_with("conversation-"));
+    assert_eq!(convo_data["convotone"], "Precise");
+}
+
+#[tokio::test]
+async fn resize_large_image() {
+    let (mut session, uploader) = session_and_uploader().await;
+    let response = session
+        .send_message_with_image("What is it?", &uploader, ImageSource::Bytes(png(400, 200)))
+        .await
+        .unwrap();
+    assert!(response.text.ends_with("/images/blob?bcid=100x50 (turn 0)"));
+}
+
+#[tokio::test]
+async fn reject_unsupported_format() {
+    let (mut session, uploader) = session_and_uploader().await;
+    let result = session
+        .send_message_with_image(
+            "What is it?",
+            &uploader,
+            ImageSource::Bytes(b"%PDF-1.7".to_vec()),
+        )
+        .await;
+    assert!(matches!(
+        result,
+        Err(ChatError::ImageUpload(ImageUploadError::UnsupportedFormat))
+    ));
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use common::frame;
+use edge_gpt::{ChatError, ChatSession, FrameDirection, ParseMode, StreamExt};
+use serde_json::json;
+
+/// A session replaying an answer without suggestions nor sources, with a field unknown to the crate.
+fn session(parse_mode: Option<ParseMode>) -> ChatSession {
+    let frames = [
+        frame(
+            FrameDirection::Sent,
+            json!({"protocol": "json", "version": 1}),
+        ),
+        frame(FrameDirection::Received, json!({})),
+        frame(
+            FrameDirection::Received,
+            json!({"type": 1, "target": "update", "arguments": [{"messages": [
+                {"text": "Hi", "author": "bot", "contentOrigin": "DeepLeo"},
+            ]}]}),
+        ),
+        frame(
+            FrameDirection::Received,
+            json!({"type": 2, "invocationId": "0", "item": {"messages": [
+                {"text": "hello", "author": "user"},
+                {"text": "Hi there", "author": "bot", "contentOrigin": "DeepLeo"},
+            ]}}),
+        ),
+        frame(
+            FrameDirection::Received,
+            json!({"type": 3, "invocationId": "0"}),
+        ),
+    ];
+    let mut session = common::replay_session(&frames);
+    session.settings_mut().parse_mode = parse_mode;
+    session
+}
+
+#[tokio::test]
+async fn strict_by_default_for_send_message() {
+    let result = session(None).send_message("hello").await;
+    assert!(matches!(
+        result,
+        Err(ChatError::GetFieldError {
+            field_name: "suggestedResponses",
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn lenient_send_message() {
+    let answer = session(Some(ParseMode::Lenient))
+        .send_message("hello")
+        .await
+        .unwrap();
+    assert_eq!(answer.text, "Hi there");
+    assert!(answer.suggested_responses.is_empty());
+    assert_eq!(answer.extra["contentOrigin"], "DeepLeo");
+    let warnings: Vec<_> = answer.warnings.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        warnings,
+        [
+            "newbing_response.item.messages[{author == bot, messageType != null}].suggestedResponses is missing",
+            "newbing_response.item.messages[{author == bot, messageType != null}].sourceAttributions is missing",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn lenient_by_default_for_streaming() {
+    let messages: Vec<_> = session(None)
+        .chat_stream("hello")
+        .await
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0].extra["contentOrigin"], "DeepLeo");
+    assert_eq!(messages[1].text, "Hi there");
+    assert_eq!(messages[1].warnings.len(), 2);
+
+    let result: Vec<_> = session(Some(ParseMode::Strict))
+        .chat_stream("hello")
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert!(matches!(
+        result.last(),
+        Some(Err(ChatError::GetFieldError { .. }))
+    ));
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use edge_gpt::{
+    ChatSession, ConversationManager, ConversationStyle, GeoLocation, Locale, StreamExt,
+};
+use serde_json::json;
+
+fn berlin() -> Locale {
+    Locale::new("de-DE").with_location(GeoLocation {
+        latitude: 52.52,
+        longitude: 13.40,
+        country: "Germany".to_string(),
+        state: "Berlin".to_string(),
+        city: "Berlin".to_string(),
+        timezone_offset: 1,
+    })
+}
+
+#[tokio::test]
+async fn locale_is_sent() {
+    let (mut settings, requests) = common::sydney_with_requests().await;
+    settings.locale = berlin();
+    let mut session = ChatSession::create_with_settings(ConversationStyle::Balanced, &[], settings)
+        .await
+        .unwrap();
+    session.send_message("hallo").await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(
+        requests[0]["create"]["accept-language"],
+        "de-DE,de;q=0.9,en;q=0.8"
+    );
+    let argument = &requests[1]["chat"]["arguments"][0];
+    assert_eq!(argument["market"], "de-DE");
+    assert_eq!(argument["region"], "DE");
+    assert_eq!(
+        argument["locationHints"],
+        json!([{
+            "country": "Germany",
+            "state": "Berlin",
+            "city": "Berlin",
+            "timezoneoffset": 1,
+            "countryConfidence": 8,
+            "Center": {"Latitude": 52.52, "Longitude": 13.40},
+            "RegionType": 2,
+            "SourceType": 1,
+        }])
+    );
+}
+
+#[tokio::test]
+async fn default_locale_has_no_location_hint() {
+    let (settings, requests) = common::sydney_with_requests().await;
+    let mut session = ChatSession::create_with_settings(ConversationStyle::Balanced, &[], settings)
+        .await
+        .unwrap();
+    session.send_message("hello").await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0]["create"]["accept-language"], "en-US,en;q=0.9");
+    let argument = &requests[1]["chat"]["arguments"][0];
+    assert_eq!(argument["market"], "en-US");
+    assert_eq!(argument["region"], "US");
+    assert_eq!(argument["locationHints"], json!([]));
+}
+
+#[tokio::test]
+async fn resumed_session_keeps_the_locale() {
+    let (mut settings, requests) = common::sydney_with_requests().await;
+    settings.locale = berlin();
+    let manager = ConversationManager::new_with_settings(&[], &settings).unwrap();
+    let list = manager.list().await.unwrap();
+    let mut session = manager
+        .resume(&list, &list.conversations[0], ConversationStyle::Balanced)
+        .await
+        .unwrap();
+    assert_eq!(session.settings().locale.market, "de-DE");
+
+    let mut stream = session.chat_stream("hallo").await.unwrap();
+    while stream.next().await.is_some() {}
+    let requests = requests.lock().unwrap();
+    let argument = &requests[0]["chat"]["arguments"][0];
+    assert_eq!(argument["conversationId"], "history-1");
+    assert_eq!(argument["market"], "de-DE");
+    assert_eq!(argument["locationHints"][0]["city"], "Berlin");
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use edge_gpt::{
+    ChatError, ChatMetrics, ChatSession, ConversationStyle, PromptLimit, StreamExt, TurnMetrics,
+    WhenTooLong,
+};
+
+#[derive(Debug, Default)]
+struct Recorder {
+    first_tokens: Mutex<Vec<Duration>>,
+    turns: Mutex<Vec<TurnMetrics>>,
+    errors: Mutex<Vec<&'static str>>,
+}
+
+impl ChatMetrics for Recorder {
+    fn record_time_to_first_token(&self, latency: Duration) {
+        self.first_tokens.lock().unwrap().push(latency);
+    }
+
+    fn record_turn(&self, metrics: &TurnMetrics) {
+        self.turns.lock().unwrap().push(*metrics);
+    }
+
+    fn record_error(&self, error: &ChatError) {
+        self.errors.lock().unwrap().push(error.kind());
+    }
+}
+
+async fn session(recorder: &Arc<Recorder>) -> ChatSession {
+    let mut settings = common::sydney().await;
+    settings.metrics = Some(recorder.clone());
+    settings.prompt_limit = PromptLimit {
+        max_chars: 20,
+        when_exceeded: WhenTooLong::Reject,
+    };
+    ChatSession::create_with_settings(ConversationStyle::Balanced, &[], settings)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn record_turns() {
+    let recorder = Arc::new(Recorder::default());
+    let mut session = session(&recorder).await;
+    session.send_message("hello").await.unwrap();
+    let _: Vec<_> = session.chat_stream("hi").await.unwrap().collect().await;
+
+    assert_eq!(recorder.first_tokens.lock().unwrap().len(), 2);
+    let turns = recorder.turns.lock().unwrap();
+    assert_eq!(turns.len(), 2);
+    for turn in turns.iter() {
+        assert!(turn.frames > 1);
+        assert!(turn.bytes > 100);
+        assert!(turn.time_to_first_token.unwrap() <= turn.latency);
+    }
+    assert!(recorder.errors.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn record_errors() {
+    let recorder = Arc::new(Recorder::default());
+    let mut session = session(&recorder).await;
+    assert!(session
+        .send_message("a prompt longer than the limit")
+        .await
+        .is_err());
+    let results: Vec<_> = session
+        .chat_stream("forbidden")
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert!(results.last().unwrap().is_err());
+    assert_eq!(
+        *recorder.errors.lock().unwrap(),
+        ["PromptTooLong", "Blocked"]
+    );
+}
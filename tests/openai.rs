@@ -0,0 +1,207 @@
+mod common;
+
+use std::{net::TcpListener, sync::Arc, time::Duration};
+
+use edge_gpt::{OpenAiServer, CONVERSATION_HEADER};
+use serde_json::{json, Value};
+
+async fn serve_with(configure: impl FnOnce(OpenAiServer) -> OpenAiServer) -> String {
+    let server = Arc::new(configure(OpenAiServer::new(vec![], common::sydney().await)));
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(server.router().into_make_service()),
+    );
+    format!("http://{addr}/v1/chat/completions")
+}
+
+async fn serve() -> String {
+    serve_with(|server| server).await
+}
+
+async fn complete(url: &str, messages: Value) -> String {
+    let response: Value = reqwest::Client::new()
+        .post(url)
+        .json(&json!({"model": "bing-precise", "messages": messages}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    response["choices"][0]["message"]["content"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+/// Send `content` in the conversation, return the reply.
+async fn complete_in(url: &str, conversation: &str, content: &str) -> String {
+    let response: Value = reqwest::Client::new()
+        .post(url)
+        .header(CONVERSATION_HEADER, conversation)
+        .json(&json!({
+            "model": "bing-precise",
+            "messages": [{"role": "user", "content": content}],
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    response["choices"][0]["message"]["content"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+/// Stream the reply to `content` in the conversation, return the data of the events.
+async fn stream_in(url: &str, conversation: &str, content: &str) -> Vec<String> {
+    let body = reqwest::Client::new()
+        .post(url)
+        .header(CONVERSATION_HEADER, conversation)
+        .json(&json!({
+            "model": "bing-creative",
+            "stream": true,
+            "messages": [{"role": "user", "content": content}],
+        }))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    body.lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.trim_start().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn continue_conversation_by_history() {
+    let url = serve().await;
+    let reply = complete(&url, json!([{"role": "user", "content": "hi"}])).await;
+    assert_eq!(reply, "You said: hi (turn 0)");
+    let reply = complete(
+        &url,
+        json!([
+            {"role": "user", "content": "hi"},
+            {"role": "assistant", "content": reply},
+            {"role": "user", "content": "again"},
+        ]),
+    )
+    .await;
+    assert_eq!(reply, "You said: again (turn 1)");
+}
+
+#[tokio::test]
+async fn stream_by_header() {
+    let url = serve().await;
+    let mut replies = vec![];
+    for content in ["hi", "again"] {
+        let events = stream_in(&url, "my-conversation", content).await;
+        assert_eq!(events.last().map(String::as_str), Some("[DONE]"));
+        let chunks: Vec<Value> = events[..events.len() - 1]
+            .iter()
+            .map(|event| serde_json::from_str(event).unwrap())
+            .collect();
+        assert_eq!(
+            chunks.last().unwrap()["choices"][0]["finish_reason"],
+            "stop"
+        );
+        let reply: String = chunks
+            .iter()
+            .filter_map(|chunk| chunk["choices"][0]["delta"]["content"].as_str())
+            .collect();
+        replies.push(reply);
+    }
+    assert_eq!(
+        replies,
+        ["You said: hi (turn 0)", "You said: again (turn 1)"]
+    );
+}
+
+#[tokio::test]
+async fn stream_rewritten_answer() {
+    let url = serve().await;
+    let events = stream_in(&url, "rewritten", "rewrite").await;
+    let contents: Vec<String> = events[..events.len() - 1]
+        .iter()
+        .map(|event| serde_json::from_str::<Value>(event).unwrap())
+        .filter_map(|chunk| {
+            let content = chunk["choices"][0]["delta"]["content"].as_str()?;
+            Some(content.to_string())
+        })
+        .collect();
+    assert_eq!(
+        contents.concat(),
+        "You said: rewrite (turn 0)You wrote: rewrite (turn 0)"
+    );
+    assert_eq!(contents.last().unwrap(), "You wrote: rewrite (turn 0)");
+}
+
+#[tokio::test]
+async fn stream_error_is_reported() {
+    let url = serve().await;
+    let events = stream_in(&url, "blocked", "something forbidden").await;
+    assert_eq!(events.last().map(String::as_str), Some("[DONE]"));
+    let chunks: Vec<Value> = events[..events.len() - 1]
+        .iter()
+        .map(|event| serde_json::from_str(event).unwrap())
+        .collect();
+    let last = chunks.last().unwrap();
+    assert_eq!(
+        last["error"]["message"],
+        "The answer has been retracted by bing"
+    );
+    assert!(chunks
+        .iter()
+        .all(|chunk| chunk["choices"][0]["finish_reason"] != "stop"));
+
+    // the failed conversation is not continued
+    let reply = complete_in(&url, "blocked", "hi").await;
+    assert_eq!(reply, "You said: hi (turn 0)");
+}
+
+#[tokio::test]
+async fn disconnected_stream_is_stored() {
+    let url = serve().await;
+    let mut response = reqwest::Client::new()
+        .post(&url)
+        .header(CONVERSATION_HEADER, "disconnected")
+        .json(&json!({
+            "model": "bing-creative",
+            "stream": true,
+            "messages": [{"role": "user", "content": "hi"}],
+        }))
+        .send()
+        .await
+        .unwrap();
+    response.chunk().await.unwrap();
+    drop(response);
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let reply = complete_in(&url, "disconnected", "again").await;
+    assert_eq!(reply, "You said: again (turn 1)");
+}
+
+#[tokio::test]
+async fn least_recently_used_is_forgotten() {
+    let url = serve_with(|server| server.with_max_sessions(1)).await;
+    complete_in(&url, "a", "hi").await;
+    complete_in(&url, "b", "hi").await;
+    assert_eq!(complete_in(&url, "a", "hi").await, "You said: hi (turn 0)");
+    assert_eq!(complete_in(&url, "a", "hi").await, "You said: hi (turn 1)");
+}
+
+#[tokio::test]
+async fn idle_session_is_forgotten() {
+    let url = serve_with(|server| server.with_idle_timeout(Duration::from_millis(100))).await;
+    complete_in(&url, "a", "hi").await;
+    assert_eq!(complete_in(&url, "a", "hi").await, "You said: hi (turn 1)");
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(complete_in(&url, "a", "hi").await, "You said: hi (turn 0)");
+}
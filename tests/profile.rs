@@ -0,0 +1,80 @@
+mod common;
+
+use edge_gpt::{
+    ChatError, ChatSession, ClientProfile, ConversationMetaCreatingError, ConversationStyle,
+    CookieStore, ImageSource, ImageUploader,
+};
+
+#[tokio::test]
+async fn headers_match_the_profile() {
+    let (mut settings, requests) = common::sydney_with_requests().await;
+    let profile = ClientProfile::edge_114_android();
+    settings.profile = profile.clone();
+    let mut session = ChatSession::create_with_settings(ConversationStyle::Balanced, &[], settings)
+        .await
+        .unwrap();
+    session.send_message("hello").await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    for headers in [&requests[0]["create"], &requests[1]["headers"]] {
+        assert_eq!(headers["user-agent"], profile.user_agent.as_str());
+        assert_eq!(headers["sec-ch-ua"], profile.brands.as_str());
+        assert_eq!(headers["sec-ch-ua-full-version"], "\"114.0.1823.43\"");
+        assert_eq!(headers["sec-ch-ua-mobile"], "?1");
+        assert_eq!(headers["sec-ch-ua-model"], "\"Pixel 7\"");
+        assert_eq!(headers["sec-ch-ua-platform"], "\"Android\"");
+        assert_eq!(headers["sec-ch-ua-platform-version"], "\"13.0.0\"");
+        assert_eq!(headers["x-ms-useragent"], profile.ms_user_agent.as_str());
+    }
+}
+
+#[tokio::test]
+async fn invalid_profile_is_rejected() {
+    let mut settings = common::sydney().await;
+    settings.profile.user_agent = "Mozilla/5.0\r\nX-Injected: 1".to_string();
+    let result =
+        ChatSession::create_with_settings(ConversationStyle::Balanced, &[], settings.clone()).await;
+    match result {
+        Err(ConversationMetaCreatingError::InvalidProfile(e)) => {
+            assert_eq!(e.header, "user-agent")
+        }
+        other => panic!("{other:?}"),
+    }
+
+    let mut session =
+        ChatSession::create_with_settings(ConversationStyle::Balanced, &[], common::sydney().await)
+            .await
+            .unwrap();
+    session.set_settings(settings);
+    assert!(matches!(
+        session.send_message("hello").await,
+        Err(ChatError::InvalidProfile(_))
+    ));
+}
+
+#[tokio::test]
+async fn upload_and_touch_use_the_profile() {
+    let (mut settings, requests) = common::sydney_with_requests().await;
+    let profile = ClientProfile::edge_114_android();
+    settings.profile = profile.clone();
+    let base_url = common::base_url(&settings);
+    let session =
+        ChatSession::create_with_settings(ConversationStyle::Balanced, &[], settings.clone())
+            .await
+            .unwrap();
+    let uploader = ImageUploader::new_with_settings(&[], session.settings())
+        .unwrap()
+        .with_base_url(&base_url);
+    uploader
+        .upload(ImageSource::Bytes(common::png(16, 8)))
+        .await
+        .unwrap();
+    let store = CookieStore::new(&[]).with_base_url(&base_url).unwrap();
+    store.touch(session.settings()).await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    for kind in ["kblob", "home"] {
+        let headers = requests.iter().find_map(|it| it.get(kind)).unwrap();
+        assert_eq!(headers["user-agent"], profile.user_agent.as_str());
+    }
+}
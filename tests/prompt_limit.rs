@@ -0,0 +1,81 @@
+mod common;
+
+use edge_gpt::{
+    ChatError, ChatSession, ConversationStyle, ImageSource, ImageUploader, PromptLimit, WhenTooLong,
+};
+
+async fn session(when_exceeded: WhenTooLong) -> ChatSession {
+    let mut settings = common::sydney().await;
+    settings.prompt_limit = PromptLimit {
+        max_chars: 400,
+        when_exceeded,
+    };
+    ChatSession::create_with_settings(ConversationStyle::Balanced, &[], settings)
+        .await
+        .unwrap()
+}
+
+fn long_prompt() -> String {
+    let mut prompt = "lorem ipsum ".repeat(60);
+    prompt.push_str("What is it about?");
+    prompt
+}
+
+#[tokio::test]
+async fn reject_long_prompt() {
+    let mut session = session(WhenTooLong::Reject).await;
+    let result = session.send_message(&long_prompt()).await;
+    assert!(matches!(
+        result,
+        Err(ChatError::PromptTooLong {
+            length: 737,
+            max_chars: 400
+        })
+    ));
+}
+
+#[tokio::test]
+async fn split_long_prompt() {
+    let mut session = session(WhenTooLong::Split).await;
+    let response = session.send_message(&long_prompt()).await.unwrap();
+    assert!(response
+        .text
+        .starts_with("You said: This is the last part, part 3 of 3, of my message."));
+    assert!(response.text.ends_with("What is it about? (turn 2)"));
+}
+
+#[tokio::test]
+async fn reject_long_prompt_by_default() {
+    let settings = common::sydney().await;
+    let mut session = ChatSession::create_with_settings(ConversationStyle::Balanced, &[], settings)
+        .await
+        .unwrap();
+    let result = session.send_message(&"lorem ipsum ".repeat(200)).await;
+    assert!(matches!(
+        result,
+        Err(ChatError::PromptTooLong {
+            length: 2400,
+            max_chars: 2000
+        })
+    ));
+}
+
+#[tokio::test]
+async fn send_long_prompt() {
+    let mut session = session(WhenTooLong::Send).await;
+    let response = session.send_message(&long_prompt()).await.unwrap();
+    assert!(response.text.ends_with("What is it about? (turn 0)"));
+}
+
+#[tokio::test]
+async fn reject_long_prompt_before_uploading() {
+    let mut session = session(WhenTooLong::Reject).await;
+    // nothing listens there, uploading would fail
+    let uploader = ImageUploader::new(&[])
+        .unwrap()
+        .with_base_url("http://127.0.0.1:1");
+    let result = session
+        .send_message_with_image(&long_prompt(), &uploader, ImageSource::Bytes(vec![]))
+        .await;
+    assert!(matches!(result, Err(ChatError::PromptTooLong { .. })));
+}
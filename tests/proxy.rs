@@ -0,0 +1,93 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use edge_gpt::{ChatSession, ConversationStyle};
+use tokio::{
+    io::{copy_bidirectional, AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// A HTTP proxy forwarding plain requests and `CONNECT` tunnels,
+/// return its url and the request lines it received.
+async fn proxy() -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(vec![]));
+    let logged = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((client, _)) = listener.accept().await {
+            tokio::spawn(forward(client, logged.clone()));
+        }
+    });
+    (url, requests)
+}
+
+async fn forward(mut client: TcpStream, requests: Arc<Mutex<Vec<String>>>) {
+    let mut head = vec![];
+    while !head.ends_with(b"\r\n\r\n") {
+        head.push(client.read_u8().await.unwrap());
+    }
+    let head = String::from_utf8(head).unwrap();
+    let request_line = head.lines().next().unwrap().to_string();
+    requests.lock().unwrap().push(request_line.clone());
+    let mut parts = request_line.split(' ');
+    let method = parts.next().unwrap();
+    let target = parts.next().unwrap();
+    let authority = target
+        .trim_start_matches("http://")
+        .split('/')
+        .next()
+        .unwrap();
+    let mut upstream = TcpStream::connect(authority).await.unwrap();
+    if method == "CONNECT" {
+        client
+            .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+            .await
+            .unwrap();
+    } else {
+        upstream.write_all(head.as_bytes()).await.unwrap();
+    }
+    copy_bidirectional(&mut client, &mut upstream).await.ok();
+}
+
+#[tokio::test]
+async fn requests_go_through_the_proxy() {
+    let (proxy, requests) = proxy().await;
+    let mut settings = common::sydney().await;
+    let create = settings.endpoints.create_conversation.clone();
+    let chat_hub = settings
+        .endpoints
+        .chat_hub
+        .trim_start_matches("ws://")
+        .split('/')
+        .next()
+        .unwrap()
+        .to_string();
+    settings.proxy = Some(proxy);
+    let mut session = ChatSession::create_with_settings(ConversationStyle::Balanced, &[], settings)
+        .await
+        .unwrap();
+    let answer = session.send_message("hello").await.unwrap();
+    assert_eq!(answer.text, "You said: hello (turn 0)");
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(
+        *requests,
+        [
+            format!("GET {create} HTTP/1.1"),
+            format!("CONNECT {chat_hub} HTTP/1.1"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn invalid_proxy_fails() {
+    let mut settings = common::sydney().await;
+    settings.proxy = Some("not a proxy".to_string());
+    assert!(
+        ChatSession::create_with_settings(ConversationStyle::Balanced, &[], settings)
+            .await
+            .is_err()
+    );
+}
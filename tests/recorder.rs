@@ -0,0 +1,71 @@
+mod common;
+
+use std::sync::Arc;
+
+use edge_gpt::{
+    ChatEvent, ChatSession, ConversationStyle, FrameDirection, FrameRecorder, StreamExt,
+};
+
+#[tokio::test]
+async fn record_and_replay_frames() {
+    let path = std::env::temp_dir().join(format!("edge-gpt-frames-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut settings = common::sydney().await;
+    settings.frame_observer = Some(Arc::new(FrameRecorder::create(&path).unwrap()));
+    let mut session = ChatSession::create_with_settings(ConversationStyle::Balanced, &[], settings)
+        .await
+        .unwrap();
+    let events: Vec<_> = session
+        .event_stream("hello")
+        .await
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+    let frames = FrameRecorder::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let sent: Vec<_> = frames
+        .iter()
+        .filter(|it| it.direction == FrameDirection::Sent)
+        .collect();
+    assert_eq!(sent[0].record, r#"{"protocol":"json","version":1}"#);
+    assert!(sent[2].record.contains(r#""text":"hello""#));
+
+    // each record is an event, the result of the turn gathers them all
+    let parsed: Vec<ChatEvent> = frames
+        .iter()
+        .filter(|it| it.direction == FrameDirection::Received)
+        .filter_map(|it| it.parse().unwrap())
+        .collect();
+    let (completed, messages) = events.split_last().unwrap();
+    assert!(matches!(completed, ChatEvent::Completed(_)));
+    assert_eq!(
+        serde_json::to_string(&parsed).unwrap(),
+        serde_json::to_string(messages).unwrap()
+    );
+
+    // replaying the recording gives the same events, result included
+    let mut session = common::replay_session(&frames);
+    let replayed: Vec<_> = session
+        .event_stream("hello")
+        .await
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    assert_eq!(
+        serde_json::to_string(&without_timing(replayed)).unwrap(),
+        serde_json::to_string(&without_timing(events)).unwrap()
+    );
+}
+
+/// The events without the time to the first token, which differs between runs.
+fn without_timing(mut events: Vec<ChatEvent>) -> Vec<ChatEvent> {
+    for event in &mut events {
+        if let ChatEvent::Completed(result) = event {
+            result.time_to_first_token = None;
+        }
+    }
+    events
+}
@@ -0,0 +1,72 @@
+use edge_gpt::{NewBingResponseMessage, RenderFormat};
+
+fn message(text: &str) -> NewBingResponseMessage {
+    NewBingResponseMessage {
+        text: text.to_string(),
+        suggested_responses: vec![],
+        suggestions: vec![],
+        source_attributions: vec![
+            "https://www.rust-lang.org/".to_string(),
+            "https://doc.rust-lang.org/book/".to_string(),
+        ],
+        parts: vec![],
+        extra: Default::default(),
+        warnings: vec![],
+    }
+}
+
+#[test]
+fn markdown_footnotes() {
+    let rendered = message("Rust is fast[^1^] and safe[^2^].").render(RenderFormat::Markdown);
+    assert_eq!(
+        rendered,
+        "Rust is fast[^1] and safe[^2].\n\n\
+         [^1]: <https://www.rust-lang.org/>\n\
+         [^2]: <https://doc.rust-lang.org/book/>"
+    );
+}
+
+#[test]
+fn plain_text_without_markers() {
+    let rendered = message("Rust is fast[^1^] & safe[^2^].").render(RenderFormat::PlainText);
+    assert_eq!(rendered, "Rust is fast & safe.");
+}
+
+#[test]
+fn html_links() {
+    let rendered = message("Rust <3[^1^]").render(RenderFormat::Html);
+    assert_eq!(
+        rendered,
+        "Rust &lt;3<sup><a href=\"https://www.rust-lang.org/\">[1]</a></sup>\n<ol>\
+         <li><a href=\"https://www.rust-lang.org/\">https://www.rust-lang.org/</a></li>\
+         <li><a href=\"https://doc.rust-lang.org/book/\">https://doc.rust-lang.org/book/</a></li>\
+         </ol>"
+    );
+}
+
+#[test]
+fn ansi_hyperlinks() {
+    let rendered = message("fast[^1^] and [^3^]").render(RenderFormat::Ansi);
+    assert!(rendered.starts_with(
+        "fast\x1b]8;;https://www.rust-lang.org/\x1b\\[1]\x1b]8;;\x1b\\ and [3]\n\n[1]: "
+    ));
+}
+
+#[test]
+fn partly_streamed_markers() {
+    for text in [
+        "Rust is fast[",
+        "Rust is fast[^",
+        "Rust is fast[^1",
+        "Rust is fast[^1^",
+    ] {
+        assert_eq!(
+            message(text).render(RenderFormat::PlainText),
+            "Rust is fast"
+        );
+    }
+    assert_eq!(
+        message("[^x^] and a[^").render(RenderFormat::PlainText),
+        "[^x^] and a"
+    );
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use common::frame;
+use edge_gpt::{ChatEvent, ChatSession, FrameDirection, ReplayTransport, StreamExt, Throttling};
+use serde_json::json;
+
+fn replayed_session(fixture: &str) -> ChatSession {
+    let transport = ReplayTransport::from_file(format!("tests/fixtures/{fixture}")).unwrap();
+    common::session_with(transport)
+}
+
+const HELLO: &str = r#"{"text":"Hello, this is Bing. How can I help?","suggested_responses":["What else is there?"],"suggestions":[{"text":"What else is there?","message_id":null,"message_type":null}],"source_attributions":[],"parts":[]}"#;
+const RUST: &str = r#"{"text":"Rust is a systems language[^1^].","suggested_responses":["What else is there?"],"suggestions":[{"text":"What else is there?","message_id":null,"message_type":null}],"source_attributions":["https://www.rust-lang.org/"],"parts":[]}"#;
+
+#[tokio::test]
+async fn replay_send_message() {
+    let mut session = replayed_session("replay.jsonl");
+    let answer = session.send_message("hello").await.unwrap();
+    assert_eq!(serde_json::to_string(&answer).unwrap(), HELLO);
+    let answer = session.send_message("what is rust?").await.unwrap();
+    assert_eq!(serde_json::to_string(&answer).unwrap(), RUST);
+}
+
+#[tokio::test]
+async fn replay_chat_stream() {
+    let mut session = replayed_session("replay.jsonl");
+    let expected = [
+        format!(
+            r#"[{{"text":"Hello","suggested_responses":[],"suggestions":[],"source_attributions":[],"parts":[]}},{{"text":"Hello, this is Bing.","suggested_responses":[],"suggestions":[],"source_attributions":[],"parts":[]}},{HELLO}]"#
+        ),
+        format!(
+            r#"[{{"text":"Rust is","suggested_responses":[],"suggestions":[],"source_attributions":[],"parts":[]}},{{"text":"Rust is a systems language[^1^]","suggested_responses":[],"suggestions":[],"source_attributions":[],"parts":[]}},{RUST}]"#
+        ),
+    ];
+    for (text, expected) in ["hello", "what is rust?"].into_iter().zip(expected) {
+        let messages: Vec<_> = session
+            .chat_stream(text)
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(serde_json::to_string(&messages).unwrap(), expected);
+    }
+}
+
+#[tokio::test]
+async fn replay_send_message_full() {
+    let mut session = replayed_session("replay.jsonl");
+    let result = session.send_message_full("hello").await.unwrap();
+    assert_eq!(serde_json::to_string(&result.message).unwrap(), HELLO);
+    let intermediate: Vec<_> = result.intermediate.iter().map(|it| &it.text).collect();
+    assert_eq!(intermediate, ["Hello", "Hello, this is Bing."]);
+    // only the updates carry it in this recording
+    assert_eq!(result.request_id.as_deref(), Some("4e1c"));
+    assert_eq!(result.throttling, None);
+    assert!(result.time_to_first_token.is_some());
+}
+
+#[tokio::test]
+async fn replay_event_stream_completed() {
+    let mut session = replayed_session("replay.jsonl");
+    session.send_message("hello").await.unwrap();
+    let events: Vec<_> = session
+        .event_stream("what is rust?")
+        .await
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    let Some(ChatEvent::Completed(result)) = events.last() else {
+        panic!("the turn should end with its result");
+    };
+    assert_eq!(serde_json::to_string(&result.message).unwrap(), RUST);
+    assert_eq!(result.intermediate.len(), 2);
+    assert!(
+        matches!(&events[events.len() - 2], ChatEvent::Message(message) if message.text == result.message.text)
+    );
+}
+
+#[tokio::test]
+async fn replay_response_parts() {
+    let mut session = replayed_session("response_parts.jsonl");
+    let answer = session.send_message("what is ownership?").await.unwrap();
+    assert_eq!(
+        serde_json::to_string(&answer.parts).unwrap(),
+        concat!(
+            r#"[{"Loader":"Generating answers for you..."},"#,
+            r#"{"SearchQuery":{"text":"Searching for: **rust ownership**","query":"rust ownership"}},"#,
+            r#"{"SearchResults":[{"title":"Understanding Ownership","url":"https://doc.rust-lang.org/book/ch04-00-understanding-ownership.html","snippets":["Ownership is Rust's most unique feature"]},"#,
+            r#"{"title":"","url":"https://www.rust-lang.org/","snippets":[]}]},"#,
+            r#"{"GenerateContent":{"content_type":"IMAGE","text":"a crab made of rust"}}]"#,
+        )
+    );
+    assert_eq!(answer.text, "Ownership is a set of rules[^1^].");
+}
+
+/// A session replaying an answer with the throttling of the conversation, without the end of
+/// the response, as when the connection closes right after the answer.
+fn closed_after_answer() -> ChatSession {
+    let frames = [
+        frame(
+            FrameDirection::Sent,
+            json!({"protocol": "json", "version": 1}),
+        ),
+        frame(FrameDirection::Received, json!({})),
+        frame(
+            FrameDirection::Received,
+            json!({"type": 1, "target": "update", "arguments": [{"messages": [
+                {"text": "Hi", "author": "bot"},
+            ]}]}),
+        ),
+        frame(
+            FrameDirection::Received,
+            json!({"type": 2, "invocationId": "0", "item": {
+                "messages": [
+                    {"text": "hello", "author": "user"},
+                    {"text": "Hi there", "author": "bot", "suggestedResponses": [], "sourceAttributions": []},
+                ],
+                "requestId": "7f2a",
+                "throttling": {"maxNumUserMessagesInConversation": 30, "numUserMessagesInConversation": 1},
+            }}),
+        ),
+    ];
+    common::replay_session(&frames)
+}
+
+#[tokio::test]
+async fn completed_when_closed_after_the_answer() {
+    let mut session = closed_after_answer();
+    let events: Vec<_> = session
+        .event_stream("hello")
+        .await
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    let Some(ChatEvent::Completed(result)) = events.last() else {
+        panic!("the turn should end with its result");
+    };
+    assert_eq!(result.message.text, "Hi there");
+    assert_eq!(result.request_id.as_deref(), Some("7f2a"));
+}
+
+#[tokio::test]
+async fn chat_stream_result() {
+    let mut session = closed_after_answer();
+    let mut stream = session.chat_stream("hello").await.unwrap();
+    assert!(stream.result().is_none());
+    let texts: Vec<_> = stream.by_ref().map(|it| it.unwrap().text).collect().await;
+    assert_eq!(texts, ["Hi", "Hi there"]);
+
+    let result = stream.result().unwrap();
+    assert_eq!(result.message.text, "Hi there");
+    let intermediate: Vec<_> = result.intermediate.iter().map(|it| &it.text).collect();
+    assert_eq!(intermediate, ["Hi"]);
+    assert_eq!(
+        result.throttling,
+        Some(Throttling {
+            user_messages: 1,
+            max_user_messages: 30,
+        })
+    );
+}
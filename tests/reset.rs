@@ -0,0 +1,91 @@
+mod common;
+
+use edge_gpt::{
+    ChatSession, ConversationMeta, ConversationMetaCreatingError, ConversationStyle, StreamExt,
+};
+
+#[tokio::test]
+async fn reset_starts_a_new_conversation() {
+    let settings = common::sydney().await;
+    let mut session = ChatSession::create_with_settings(ConversationStyle::Precise, &[], settings)
+        .await
+        .unwrap();
+    session.send_message("hello").await.unwrap();
+    session.send_message("hello again").await.unwrap();
+
+    let previous = session.reset().await.unwrap();
+    assert_ne!(
+        previous.conversation_meta().conversation_id,
+        session.conversation_meta().conversation_id
+    );
+    assert!(matches!(session.style(), ConversationStyle::Precise));
+    assert_eq!(
+        session.settings().endpoints.chat_hub,
+        previous.settings().endpoints.chat_hub
+    );
+
+    let answer = session.send_message("hello").await.unwrap();
+    assert!(answer.text.ends_with("(turn 0)"), "{}", answer.text);
+}
+
+#[tokio::test]
+async fn reset_archives_the_transcript() {
+    let settings = common::sydney().await;
+    let mut session = ChatSession::create_with_settings(ConversationStyle::Balanced, &[], settings)
+        .await
+        .unwrap();
+    session.send_message("hello").await.unwrap();
+    let _: Vec<_> = session.chat_stream("again").await.unwrap().collect().await;
+    assert!(session.send_message("forbidden").await.is_err());
+
+    let previous = session.reset().await.unwrap();
+    let transcript: Vec<_> = previous
+        .transcript()
+        .into_iter()
+        .map(|it| (it.prompt, it.answer.text))
+        .collect();
+    assert_eq!(
+        transcript,
+        [
+            ("hello".to_string(), "You said: hello (turn 0)".to_string()),
+            ("again".to_string(), "You said: again (turn 1)".to_string()),
+        ]
+    );
+    assert!(session.transcript().is_empty());
+    session.send_message("hello").await.unwrap();
+    assert_eq!(session.transcript().len(), 1);
+    assert_eq!(previous.transcript().len(), 2);
+
+    // the transcript is dumped with the session
+    let loaded: ChatSession =
+        serde_json::from_str(&serde_json::to_string(&previous).unwrap()).unwrap();
+    assert_eq!(loaded.transcript().len(), 2);
+}
+
+#[tokio::test]
+async fn reset_needs_cookies() {
+    let mut session = ChatSession::from_meta(
+        ConversationMeta::new("id".into(), "signature".into(), "client".into()),
+        ConversationStyle::Balanced,
+        0,
+    );
+    assert!(session.cookie_store().is_none());
+    assert!(matches!(
+        session.reset().await,
+        Err(ConversationMetaCreatingError::NoCookies)
+    ));
+
+    let mut session: ChatSession =
+        serde_json::from_str(&serde_json::to_string(&session).unwrap()).unwrap();
+    assert!(matches!(
+        session.reset().await,
+        Err(ConversationMetaCreatingError::NoCookies)
+    ));
+
+    // a loaded session resets once given cookies
+    session.set_settings(common::sydney().await);
+    session.set_cookies(&[]);
+    let previous = session.reset().await.unwrap();
+    assert_eq!(previous.conversation_meta().conversation_id, "id");
+    assert_ne!(session.conversation_meta().conversation_id, "id");
+}
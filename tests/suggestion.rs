@@ -0,0 +1,52 @@
+mod common;
+
+use std::sync::Arc;
+
+use edge_gpt::{
+    ChatSession, ConversationStyle, FrameDirection, FrameRecorder, NewBingResponseMessage,
+};
+use serde_json::Value;
+
+#[tokio::test]
+async fn send_suggestion() {
+    let path =
+        std::env::temp_dir().join(format!("edge-gpt-suggestion-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut settings = common::sydney().await;
+    settings.frame_observer = Some(Arc::new(FrameRecorder::create(&path).unwrap()));
+    let mut session = ChatSession::create_with_settings(ConversationStyle::Balanced, &[], settings)
+        .await
+        .unwrap();
+    let answer = session.send_message("hello").await.unwrap();
+    assert_eq!(answer.suggested_responses, ["Tell me more"]);
+    let suggestion = &answer.suggestions[0];
+    assert_eq!(suggestion.message_id.as_deref(), Some("suggestion-1"));
+
+    let answer = session.send_suggestion(suggestion).await.unwrap();
+    assert_eq!(answer.text, "You said: Tell me more (turn 1)");
+
+    let frames = FrameRecorder::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let request: Value = frames
+        .iter()
+        .rev()
+        .filter(|it| it.direction == FrameDirection::Sent)
+        .map(|it| serde_json::from_str::<Value>(&it.record).unwrap())
+        .find(|it| it["type"] == 4)
+        .unwrap();
+    let message = &request["arguments"][0]["message"];
+    assert_eq!(message["text"], "Tell me more");
+    assert_eq!(message["inputMethod"], "Suggestion");
+    assert_eq!(message["messageId"], "suggestion-1");
+    assert_eq!(message["messageType"], "Suggestion");
+}
+
+#[test]
+fn answers_without_suggestions_parse() {
+    let answer: NewBingResponseMessage = serde_json::from_str(
+        r#"{"text":"Hi","suggested_responses":["Tell me more"],"source_attributions":[]}"#,
+    )
+    .unwrap();
+    assert_eq!(answer.suggested_responses, ["Tell me more"]);
+    assert!(answer.suggestions.is_empty());
+}
@@ -0,0 +1,216 @@
+mod common;
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use edge_gpt::{
+    ChatError, ChatSession, Connection, ConversationStyle, SessionResult, StreamExt, Transport,
+};
+use futures_util::future::BoxFuture;
+use reqwest::header::HeaderMap;
+use serde_json::json;
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Metadata, Subscriber,
+};
+
+/// The message and fields of an event.
+#[derive(Debug, Default)]
+struct Logged {
+    message: String,
+    fields: Vec<(String, String)>,
+}
+
+impl Visit for Logged {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let value = format!("{value:?}");
+        match field.name() {
+            "message" => self.message = value,
+            name => self.fields.push((name.to_string(), value)),
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record_debug(field, &format_args!("{value}"));
+    }
+}
+
+/// Keeps the events of the thread, the spans are not tracked.
+#[derive(Clone, Default)]
+struct Events(Arc<Mutex<Vec<Logged>>>);
+
+impl Events {
+    fn messages(&self) -> Vec<String> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|it| it.message.clone())
+            .collect()
+    }
+
+    fn find(&self, message: &str) -> Option<Vec<(String, String)>> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|it| it.message == message)
+            .map(|it| it.fields.clone())
+    }
+}
+
+impl Subscriber for Events {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut logged = Logged::default();
+        event.record(&mut logged);
+        self.0.lock().unwrap().push(logged);
+    }
+
+    fn enter(&self, _span: &span::Id) {}
+
+    fn exit(&self, _span: &span::Id) {}
+}
+
+fn field<'a>(fields: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    fields
+        .iter()
+        .find(|(it, _)| it == name)
+        .map(|(_, value)| value.as_str())
+}
+
+#[tokio::test]
+async fn turn_completed() {
+    let events = Events::default();
+    let _guard = tracing::subscriber::set_default(events.clone());
+    let settings = common::sydney().await;
+    let mut session = ChatSession::create_with_settings(ConversationStyle::Balanced, &[], settings)
+        .await
+        .unwrap();
+    session.send_message("hello").await.unwrap();
+
+    let messages = events.messages();
+    assert!(messages.contains(&"conversation created".to_string()));
+    assert!(messages.contains(&"first token".to_string()));
+    let fields = events.find("turn completed").unwrap();
+    // the handshake, 5 words and the answer
+    assert_eq!(field(&fields, "frames"), Some("7"));
+    assert!(events.find("turn ended without an answer").is_none());
+}
+
+#[tokio::test]
+async fn blocked_turn_ended() {
+    let events = Events::default();
+    let _guard = tracing::subscriber::set_default(events.clone());
+    let settings = common::sydney().await;
+    let mut session = ChatSession::create_with_settings(ConversationStyle::Balanced, &[], settings)
+        .await
+        .unwrap();
+    let result = session.send_message("forbidden").await;
+    assert!(matches!(result, Err(ChatError::Blocked)));
+    let results: Vec<_> = session
+        .chat_stream("forbidden")
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert!(results.last().unwrap().is_err());
+
+    let ended: Vec<_> = events
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|it| it.message == "turn ended without an answer")
+        .map(|it| field(&it.fields, "outcome").unwrap().to_string())
+        .collect();
+    assert_eq!(ended, ["blocked", "blocked"]);
+    assert!(events.find("turn completed").is_none());
+}
+
+#[tokio::test]
+async fn ended_conversation_traced() {
+    let events = Events::default();
+    let _guard = tracing::subscriber::set_default(events.clone());
+    let settings = common::sydney().await;
+    let mut session = ChatSession::create_with_settings(ConversationStyle::Balanced, &[], settings)
+        .await
+        .unwrap();
+    let result = session.send_message("goodbye").await;
+    assert!(matches!(result, Err(ChatError::ConversationEnded)));
+
+    let fields = events.find("turn ended without an answer").unwrap();
+    assert_eq!(field(&fields, "outcome"), Some("conversation ended"));
+    assert!(events.find("turn completed").is_none());
+}
+
+/// Receives all the records of the turn in a single websocket message.
+#[derive(Debug)]
+struct Batched(Vec<String>);
+
+struct BatchedConnection(Vec<Vec<String>>);
+
+impl Transport for Batched {
+    fn connect<'a>(
+        &'a self,
+        _url: &'a str,
+        _headers: HeaderMap,
+    ) -> BoxFuture<'a, SessionResult<Box<dyn Connection>>> {
+        let frames = vec![self.0.clone(), vec!["{}".to_string()]];
+        Box::pin(async move { Ok(Box::new(BatchedConnection(frames)) as Box<dyn Connection>) })
+    }
+}
+
+impl Connection for BatchedConnection {
+    fn send(&mut self, _record: String) -> BoxFuture<'_, SessionResult<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn receive(&mut self) -> BoxFuture<'_, Option<SessionResult<Vec<String>>>> {
+        Box::pin(async move { self.0.pop().map(Ok) })
+    }
+}
+
+#[tokio::test]
+async fn records_counted() {
+    let events = Events::default();
+    let _guard = tracing::subscriber::set_default(events.clone());
+    let records = [
+        json!({"type": 1, "target": "update", "arguments": [{"messages": [
+            {"text": "Hi", "author": "bot"},
+        ]}]}),
+        json!({"type": 2, "invocationId": "0", "item": {"messages": [
+            {"text": "hello", "author": "user"},
+            {"text": "Hi there", "author": "bot", "suggestedResponses": [], "sourceAttributions": []},
+        ]}}),
+        json!({"type": 3, "invocationId": "0"}),
+    ]
+    .map(|it| it.to_string());
+    let mut session = common::session_with(Batched(records.to_vec()));
+    let _: Vec<_> = session.chat_stream("hello").await.unwrap().collect().await;
+
+    let received = events
+        .messages()
+        .into_iter()
+        .filter(|it| it == "record received")
+        .count();
+    assert_eq!(received, 4);
+    let fields = events.find("turn completed").unwrap();
+    assert_eq!(field(&fields, "frames"), Some("4"));
+    // the handshake and the records, with their delimiters
+    let bytes = 3 + records.iter().map(|it| it.len() + 1).sum::<usize>();
+    assert_eq!(field(&fields, "bytes"), Some(bytes.to_string().as_str()));
+}